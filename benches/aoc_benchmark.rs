@@ -48,21 +48,47 @@ bench_please!(day2);
 bench_please!(day3);
 bench_please_mut!(day4);
 bench_please!(day5);
-bench_please!(day6);
-bench_please!(day7, part1_sorted, part2_naive);
-bench_please!(day8, part2_alternate);
-bench_please!(day9);
-bench_please!(day10);
-bench_please!(day11);
-bench_please!(day12);
-bench_please!(day13);
+bench_please!(day6, part2_matrix);
+bench_please!(day7, part1_sorted, part2_naive, part2_ternary);
+bench_please!(day8, part2_alternate, part2_generic, part2_bitmask);
+bench_please!(day9, part2_union_find);
+
+pub fn day9_synthetic(c: &mut Criterion) {
+    // Kept small enough that the recursive `part2` doesn't overflow the stack
+    let raw_input = day9::generate_height_map(150, 150, 2021);
+    let input = day9::input_generator(&raw_input);
+    c.bench_function("day9 synthetic part 2", |b| b.iter(|| day9::part2(&input)));
+    c.bench_function("day9 synthetic part2_union_find", |b| {
+        b.iter(|| day9::part2_union_find(&input))
+    });
+}
+bench_please!(day10, part1_streaming, part2_streaming);
+bench_please!(day11, part1_dense, part2_dense);
+bench_please!(day12, part1_memoized, part2_memoized, part2_policy);
+bench_please!(day13, part2_grid);
 bench_please!(day14, part1_slow);
 bench_please!(day15);
 bench_please!(day16);
 
 criterion_group!(
-    all, day1, day2, day3, day4, day5, day6, day7, day8, day9, day10, day11, day12, day13, day14,
-    day15, day16
+    all,
+    day1,
+    day2,
+    day3,
+    day4,
+    day5,
+    day6,
+    day7,
+    day8,
+    day9,
+    day9_synthetic,
+    day10,
+    day11,
+    day12,
+    day13,
+    day14,
+    day15,
+    day16
 );
 criterion_group!(single, day16);
 criterion_main!(single);
@@ -63,7 +63,7 @@ pub fn part2_orig(input: &[u32]) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -75,7 +75,7 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
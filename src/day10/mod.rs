@@ -1,194 +1,538 @@
-#[derive(Debug, PartialEq)]
-pub enum Token {
-    OpenParen,
-    CloseParen,
-    OpenSquare,
-    CloseSquare,
-    OpenCurly,
-    CloseCurly,
-    OpenFish,
-    CloseFish,
-    Invalid,
+use std::{fmt::Write, io::BufRead};
+
+/// An opening and closing character along with the scores the puzzle assigns them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BracketPair {
+    pub open: char,
+    pub close: char,
+    /// Score when `close` is found where a different closer was expected
+    pub corrupted_score: usize,
+    /// Value of `close` when it is needed to complete a line
+    pub completion_score: usize,
 }
 
-pub fn input_generator(input: &str) -> String {
-    input.to_string()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineStatus {
+    Valid,
+    /// `column` is 1-based, `expected` is `None` when nothing was open
+    Corrupted {
+        column: usize,
+        expected: Option<char>,
+        found: char,
+    },
+    Incomplete {
+        completion: String,
+    },
 }
 
-pub fn part1(input: &str) -> usize {
-    input
-        .lines()
-        .map(|line| {
-            let mut token_stream = Vec::with_capacity(line.len() * std::mem::size_of::<Token>());
-            line.chars()
-                .map(|c| match c {
-                    '(' => {
-                        token_stream.push(Token::OpenParen);
-                        0
-                    }
-                    '[' => {
-                        token_stream.push(Token::OpenSquare);
-                        0
-                    }
-                    '{' => {
-                        token_stream.push(Token::OpenCurly);
-                        0
-                    }
-                    '<' => {
-                        token_stream.push(Token::OpenFish);
-                        0
-                    }
-                    ')' => {
-                        if let Some(tok) = token_stream.pop() {
-                            if tok == Token::OpenParen {
-                                0
-                            } else {
-                                3
-                            }
-                        } else {
-                            panic!("More closing tokens than opening tokens, is this corrupted?")
-                        }
-                    }
-                    ']' => {
-                        if let Some(tok) = token_stream.pop() {
-                            if tok == Token::OpenSquare {
-                                0
-                            } else {
-                                57
-                            }
-                        } else {
-                            panic!("More closing tokens than opening tokens, is this corrupted?")
-                        }
-                    }
-                    '}' => {
-                        if let Some(tok) = token_stream.pop() {
-                            if tok == Token::OpenCurly {
-                                0
-                            } else {
-                                1197
-                            }
-                        } else {
-                            panic!("More closing tokens than opening tokens, is this corrupted?")
-                        }
-                    }
-                    '>' => {
-                        if let Some(tok) = token_stream.pop() {
-                            if tok == Token::OpenFish {
-                                0
-                            } else {
-                                25137
-                            }
-                        } else {
-                            panic!("More closing tokens than opening tokens, is this corrupted?")
-                        }
-                    }
-                    _ => {
-                        token_stream.push(Token::Invalid);
-                        0
-                    }
-                })
-                .sum::<usize>()
-        })
-        .sum::<usize>()
+/// A single change to a line, `column` is 1-based in the original line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Substitute {
+        column: usize,
+        found: char,
+        replacement: char,
+    },
+    Delete {
+        column: usize,
+        found: char,
+    },
 }
 
-pub fn part2(input: &str) -> usize {
-    let mut scores = Vec::new();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repair {
+    /// Edits in column order, empty if the line wasn't corrupted
+    pub edits: Vec<Edit>,
+    /// The line after the edits, which is no longer corrupted but may still be incomplete
+    pub line: String,
+    /// Closing sequence needed after `line`
+    pub completion: String,
+}
 
-    'lines: for line in input.lines() {
-        let mut token_stream = Vec::with_capacity(line.len() * std::mem::size_of::<Token>());
+impl Repair {
+    /// The repaired line with its completion appended, which is always valid
+    pub fn fixed_line(&self) -> String {
+        format!("{}{}", self.line, self.completion)
+    }
+}
 
-        for c in line.chars() {
-            match c {
-                '(' => {
-                    token_stream.push(Token::OpenParen);
-                }
-                '[' => {
-                    token_stream.push(Token::OpenSquare);
+/// How `BracketLinter::repair` made a range of characters balanced
+#[derive(Debug, Clone, Copy)]
+enum Balance {
+    Empty,
+    Delete,
+    /// Match the first character of the range with the one at `close`, as `pair`
+    Match {
+        close: usize,
+        pair: usize,
+    },
+}
+
+/// Both puzzle answers gathered in one pass by `BracketLinter::check_stream`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamSummary {
+    pub syntax_error_score: usize,
+    /// Median completion score, `None` when no line was incomplete
+    pub completion_score: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BracketLinter {
+    pairs: Vec<BracketPair>,
+    completion_multiplier: usize,
+}
+
+impl BracketLinter {
+    pub fn new(pairs: Vec<BracketPair>, completion_multiplier: usize) -> Self {
+        BracketLinter {
+            pairs,
+            completion_multiplier,
+        }
+    }
+
+    /// The four bracket pairs and scores used by the navigation subsystem
+    pub fn navigation() -> Self {
+        BracketLinter::new(
+            vec![
+                BracketPair {
+                    open: '(',
+                    close: ')',
+                    corrupted_score: 3,
+                    completion_score: 1,
+                },
+                BracketPair {
+                    open: '[',
+                    close: ']',
+                    corrupted_score: 57,
+                    completion_score: 2,
+                },
+                BracketPair {
+                    open: '{',
+                    close: '}',
+                    corrupted_score: 1197,
+                    completion_score: 3,
+                },
+                BracketPair {
+                    open: '<',
+                    close: '>',
+                    corrupted_score: 25137,
+                    completion_score: 4,
+                },
+            ],
+            5,
+        )
+    }
+
+    fn opener(&self, c: char) -> Option<usize> {
+        self.pairs.iter().position(|pair| pair.open == c)
+    }
+
+    fn closer(&self, c: char) -> Option<usize> {
+        self.pairs.iter().position(|pair| pair.close == c)
+    }
+
+    pub fn lint(&self, line: &str) -> LineStatus {
+        let mut open_pairs = Vec::new();
+
+        for (idx, c) in line.chars().enumerate() {
+            if let Some(pair) = self.opener(c) {
+                open_pairs.push(pair);
+                continue;
+            }
+
+            let expected = open_pairs.pop();
+            if expected.is_none() || expected != self.closer(c) {
+                return LineStatus::Corrupted {
+                    column: idx + 1,
+                    expected: expected.map(|pair| self.pairs[pair].close),
+                    found: c,
+                };
+            }
+        }
+
+        if open_pairs.is_empty() {
+            LineStatus::Valid
+        } else {
+            LineStatus::Incomplete {
+                completion: open_pairs
+                    .iter()
+                    .rev()
+                    .map(|&pair| self.pairs[pair].close)
+                    .collect(),
+            }
+        }
+    }
+
+    /// Score of the illegal character in a corrupted line, 0 for anything else
+    pub fn syntax_error_score(&self, status: &LineStatus) -> usize {
+        match status {
+            LineStatus::Corrupted { found, .. } => self
+                .closer(*found)
+                .map_or(0, |pair| self.pairs[pair].corrupted_score),
+            _ => 0,
+        }
+    }
+
+    /// Score of the completion string of an incomplete line
+    pub fn completion_score(&self, status: &LineStatus) -> Option<usize> {
+        match status {
+            LineStatus::Incomplete { completion } => {
+                Some(completion.chars().fold(0, |score, c| {
+                    let pair = self.closer(c).expect("Completions only contain closers");
+                    score * self.completion_multiplier + self.pairs[pair].completion_score
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// The exact closing sequence for a line, empty when it is already valid and `None` when it is corrupted
+    pub fn complete(&self, line: &str) -> Option<String> {
+        match self.lint(line) {
+            LineStatus::Valid => Some(String::new()),
+            LineStatus::Incomplete { completion } => Some(completion),
+            LineStatus::Corrupted { .. } => None,
+        }
+    }
+
+    /// Cheapest way to make `open` and `close` a matching pair, returns the number of substitutions needed
+    /// along with the pair to use
+    fn match_cost(&self, open: char, close: char) -> (usize, usize) {
+        self.pairs
+            .iter()
+            .enumerate()
+            .map(|(idx, pair)| {
+                (
+                    (pair.open != open) as usize + (pair.close != close) as usize,
+                    idx,
+                )
+            })
+            .min()
+            .expect("A linter needs at least one bracket pair")
+    }
+
+    /// Find the fewest substitutions and deletions that stop `line` from being corrupted.
+    ///
+    /// Any line that isn't corrupted is made of balanced runs separated by openers that are never closed, so
+    /// `balance[i][j]` holds the cheapest way to balance `chars[i..j]` and a second pass picks where the balanced
+    /// runs go. This is O(n^3) in the line length.
+    pub fn repair(&self, line: &str) -> Repair {
+        let chars: Vec<char> = line.chars().collect();
+        let len = chars.len();
+
+        let mut cost = vec![vec![0; len + 1]; len + 1];
+        let mut balance = vec![vec![Balance::Empty; len + 1]; len + 1];
+        for width in 1..=len {
+            for start in 0..=len - width {
+                let end = start + width;
+
+                let mut best = (usize::MAX, Balance::Delete);
+                for close in start + 1..end {
+                    let (substitutions, pair) = self.match_cost(chars[start], chars[close]);
+                    let total = substitutions + cost[start + 1][close] + cost[close + 1][end];
+                    if total < best.0 {
+                        best = (total, Balance::Match { close, pair });
+                    }
                 }
-                '{' => {
-                    token_stream.push(Token::OpenCurly);
+                // Prefer substituting over deleting when they cost the same
+                if 1 + cost[start + 1][end] < best.0 {
+                    best = (1 + cost[start + 1][end], Balance::Delete);
                 }
-                '<' => {
-                    token_stream.push(Token::OpenFish);
+
+                cost[start][end] = best.0;
+                balance[start][end] = best.1;
+            }
+        }
+
+        // prefix[j] is the cheapest way to make `chars[..j]` uncorrupted as (edits, openers left unclosed), so ties
+        // prefer repairs that need less completing. `split[j]` is where its last balanced run starts, or `None` if
+        // `chars[j - 1]` is left open (or deleted when it isn't an opener)
+        let mut prefix = vec![(0, 0); len + 1];
+        let mut split = vec![None; len + 1];
+        for end in 1..=len {
+            let (edits, open) = prefix[end - 1];
+            prefix[end] = match self.opener(chars[end - 1]) {
+                Some(_) => (edits, open + 1),
+                None => (edits + 1, open),
+            };
+            for start in 0..end - 1 {
+                let candidate = (prefix[start].0 + cost[start][end], prefix[start].1);
+                if candidate <= prefix[end] {
+                    prefix[end] = candidate;
+                    split[end] = Some(start);
                 }
-                ')' => {
-                    if let Some(tok) = token_stream.pop() {
-                        if tok == Token::OpenParen {
-                        } else {
-                            continue 'lines;
-                        }
-                    } else {
-                        panic!("More closing tokens than opening tokens, is this corrupted?")
-                    }
+            }
+        }
+
+        let mut edits = Vec::with_capacity(prefix[len].0);
+        let mut ranges = Vec::new();
+        let mut end = len;
+        while end > 0 {
+            match split[end] {
+                Some(start) => {
+                    ranges.push((start, end));
+                    end = start;
                 }
-                ']' => {
-                    if let Some(tok) = token_stream.pop() {
-                        if tok == Token::OpenSquare {
-                        } else {
-                            continue 'lines;
-                        }
-                    } else {
-                        panic!("More closing tokens than opening tokens, is this corrupted?")
+                None => {
+                    if self.opener(chars[end - 1]).is_none() {
+                        edits.push(Edit::Delete {
+                            column: end,
+                            found: chars[end - 1],
+                        });
                     }
+                    end -= 1;
                 }
-                '}' => {
-                    if let Some(tok) = token_stream.pop() {
-                        if tok == Token::OpenCurly {
-                        } else {
-                            continue 'lines;
-                        }
-                    } else {
-                        panic!("More closing tokens than opening tokens, is this corrupted?")
-                    }
+            }
+        }
+
+        while let Some((start, end)) = ranges.pop() {
+            match balance[start][end] {
+                Balance::Empty => {}
+                Balance::Delete => {
+                    edits.push(Edit::Delete {
+                        column: start + 1,
+                        found: chars[start],
+                    });
+                    ranges.push((start + 1, end));
                 }
-                '>' => {
-                    if let Some(tok) = token_stream.pop() {
-                        if tok == Token::OpenFish {
-                        } else {
-                            continue 'lines;
+                Balance::Match { close, pair } => {
+                    let pair = self.pairs[pair];
+                    for (idx, replacement) in [(start, pair.open), (close, pair.close)] {
+                        if chars[idx] != replacement {
+                            edits.push(Edit::Substitute {
+                                column: idx + 1,
+                                found: chars[idx],
+                                replacement,
+                            });
                         }
-                    } else {
-                        panic!("More closing tokens than opening tokens, is this corrupted?")
                     }
-                }
-                _ => {
-                    token_stream.push(Token::Invalid);
+                    ranges.push((start + 1, close));
+                    ranges.push((close + 1, end));
                 }
             }
         }
 
-        // Score remaining line
-        let mut score = 0;
+        edits.sort_by_key(|edit| match edit {
+            Edit::Substitute { column, .. } | Edit::Delete { column, .. } => *column,
+        });
 
-        for tok in token_stream.iter().rev() {
-            match tok {
-                Token::OpenParen => {
-                    score = score * 5 + 1;
+        let mut repaired = chars;
+        for edit in edits.iter().rev() {
+            match *edit {
+                Edit::Substitute {
+                    column,
+                    replacement,
+                    ..
+                } => repaired[column - 1] = replacement,
+                Edit::Delete { column, .. } => {
+                    repaired.remove(column - 1);
                 }
-                Token::OpenSquare => {
-                    score = score * 5 + 2;
+            }
+        }
+        let line: String = repaired.into_iter().collect();
+        let completion = self
+            .complete(&line)
+            .expect("Repaired line should not be corrupted");
+
+        Repair {
+            edits,
+            line,
+            completion,
+        }
+    }
+
+    /// Check every line of `reader` in a single pass without holding on to any lines. Only the stack of open
+    /// brackets is kept for the current line, but an exact median needs every completion score, so one `usize`
+    /// is also kept per incomplete line. Memory is O(max depth + incomplete lines), not O(max depth).
+    /// Completion scores saturate at `usize::MAX` for very deeply nested lines.
+    pub fn check_stream<R: BufRead>(&self, mut reader: R) -> std::io::Result<StreamSummary> {
+        let mut syntax_error_score = 0;
+        let mut completion_scores = Vec::new();
+
+        let mut open_pairs = Vec::new();
+        let mut corrupted = false;
+        // Bytes of a UTF-8 character split across two reads
+        let mut pending = Vec::with_capacity(4);
+
+        let mut finish_line = |open_pairs: &mut Vec<usize>, corrupted: &mut bool| {
+            if !*corrupted && !open_pairs.is_empty() {
+                completion_scores.push(open_pairs.iter().rev().fold(0usize, |score, &pair| {
+                    score
+                        .saturating_mul(self.completion_multiplier)
+                        .saturating_add(self.pairs[pair].completion_score)
+                }));
+            }
+            open_pairs.clear();
+            *corrupted = false;
+        };
+
+        loop {
+            let chunk = reader.fill_buf()?;
+            if chunk.is_empty() {
+                break;
+            }
+            let consumed = chunk.len();
+
+            for &byte in chunk {
+                if byte == b'\n' {
+                    pending.clear();
+                    finish_line(&mut open_pairs, &mut corrupted);
+                    continue;
                 }
-                Token::OpenCurly => {
-                    score = score * 5 + 3;
+                // The rest of a corrupted line doesn't matter, and '\n' never shows up inside a UTF-8 character
+                if corrupted || byte == b'\r' {
+                    continue;
                 }
-                Token::OpenFish => {
-                    score = score * 5 + 4;
+
+                pending.push(byte);
+                let c = match std::str::from_utf8(&pending) {
+                    Ok(decoded) => decoded.chars().next().expect("pending is never empty"),
+                    Err(err) if err.error_len().is_none() => continue,
+                    Err(_) => char::REPLACEMENT_CHARACTER,
+                };
+                pending.clear();
+
+                if let Some(pair) = self.opener(c) {
+                    open_pairs.push(pair);
+                } else {
+                    let expected = open_pairs.pop();
+                    if expected.is_none() || expected != self.closer(c) {
+                        syntax_error_score += self
+                            .closer(c)
+                            .map_or(0, |pair| self.pairs[pair].corrupted_score);
+                        corrupted = true;
+                    }
                 }
-                _ => unreachable!("Remaining token is not an Open* Token"),
             }
+
+            reader.consume(consumed);
+        }
+        finish_line(&mut open_pairs, &mut corrupted);
+
+        let middle = completion_scores.len() / 2;
+        let completion_score = if completion_scores.is_empty() {
+            None
+        } else {
+            Some(*completion_scores.select_nth_unstable(middle).1)
+        };
+
+        Ok(StreamSummary {
+            syntax_error_score,
+            completion_score,
+        })
+    }
+
+    /// Compiler style diagnostics for every line that isn't valid
+    pub fn report(&self, input: &str) -> String {
+        let mut report = String::new();
+        let lines: Vec<&str> = input.lines().collect();
+        let gutter = lines.len().to_string().len();
+
+        for (idx, line) in lines.iter().enumerate() {
+            let (level, message, column, label) = match self.lint(line) {
+                LineStatus::Valid => continue,
+                LineStatus::Corrupted {
+                    column,
+                    expected: Some(expected),
+                    found,
+                } => (
+                    "error",
+                    format!("expected `{}`, found `{}`", expected, found),
+                    column,
+                    format!("expected `{}`", expected),
+                ),
+                LineStatus::Corrupted {
+                    column,
+                    expected: None,
+                    found,
+                } => (
+                    "error",
+                    format!("unexpected `{}` with nothing left to close", found),
+                    column,
+                    "unexpected closer".to_string(),
+                ),
+                LineStatus::Incomplete { completion } => (
+                    "warning",
+                    "incomplete line".to_string(),
+                    line.chars().count() + 1,
+                    format!("missing `{}`", completion),
+                ),
+            };
+
+            writeln!(report, "{}: {}", level, message).expect("Writing to a String can't fail");
+            writeln!(
+                report,
+                "{:>gutter$}--> line {}:{}",
+                "",
+                idx + 1,
+                column,
+                gutter = gutter
+            )
+            .expect("Writing to a String can't fail");
+            writeln!(report, "{:>gutter$} |", "", gutter = gutter)
+                .expect("Writing to a String can't fail");
+            writeln!(report, "{:>gutter$} | {}", idx + 1, line, gutter = gutter)
+                .expect("Writing to a String can't fail");
+            writeln!(
+                report,
+                "{:>gutter$} | {:>column$} {}",
+                "",
+                "^",
+                label,
+                gutter = gutter,
+                column = column
+            )
+            .expect("Writing to a String can't fail");
+            writeln!(report).expect("Writing to a String can't fail");
         }
 
-        scores.push(score);
+        report
     }
+}
+
+pub fn input_generator(input: &str) -> &str {
+    input
+}
+
+pub fn part1(input: &str) -> usize {
+    let linter = BracketLinter::navigation();
+
+    input
+        .lines()
+        .map(|line| linter.syntax_error_score(&linter.lint(line)))
+        .sum::<usize>()
+}
+
+pub fn part2(input: &str) -> usize {
+    let linter = BracketLinter::navigation();
+
+    let mut scores: Vec<usize> = input
+        .lines()
+        .filter_map(|line| linter.completion_score(&linter.lint(line)))
+        .collect();
     scores.sort();
 
     scores[scores.len() / 2]
 }
 
+pub fn part1_streaming(input: &str) -> usize {
+    BracketLinter::navigation()
+        .check_stream(input.as_bytes())
+        .expect("Reading from a &[u8] can't fail")
+        .syntax_error_score
+}
+
+pub fn part2_streaming(input: &str) -> usize {
+    BracketLinter::navigation()
+        .check_stream(input.as_bytes())
+        .expect("Reading from a &[u8] can't fail")
+        .completion_score
+        .expect("No incomplete lines to score")
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -200,11 +544,175 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
 
     test!(part1, 26397);
     test!(part2, 288957);
+    test!(part1_streaming, 26397);
+    test!(part2_streaming, 288957);
+
+    #[test]
+    fn lint_lines() {
+        use super::{BracketLinter, LineStatus};
+
+        let linter = BracketLinter::navigation();
+        assert_eq!(linter.lint("([]){<>}"), LineStatus::Valid);
+        assert_eq!(
+            linter.lint("{([(<{}[<>[]}>{[]{[(<()>"),
+            LineStatus::Corrupted {
+                column: 13,
+                expected: Some(']'),
+                found: '}'
+            }
+        );
+        assert_eq!(
+            linter.lint("())"),
+            LineStatus::Corrupted {
+                column: 3,
+                expected: None,
+                found: ')'
+            }
+        );
+
+        let incomplete = linter.lint("[({(<(())[]>[[{[]{<()<>>");
+        assert_eq!(
+            incomplete,
+            LineStatus::Incomplete {
+                completion: "}}]])})]".to_string()
+            }
+        );
+        assert_eq!(linter.completion_score(&incomplete), Some(288957));
+        assert_eq!(
+            linter.complete("[({(<(())[]>[[{[]{<()<>>"),
+            Some("}}]])})]".to_string())
+        );
+        assert_eq!(linter.complete("[]"), Some(String::new()));
+        assert_eq!(linter.complete("[)"), None);
+    }
+
+    #[test]
+    fn repair_lines() {
+        use super::{BracketLinter, Edit, LineStatus};
+
+        let linter = BracketLinter::navigation();
+
+        let repair = linter.repair("(]");
+        assert_eq!(
+            repair.edits,
+            vec![Edit::Substitute {
+                column: 2,
+                found: ']',
+                replacement: ')'
+            }]
+        );
+        assert_eq!(repair.fixed_line(), "()");
+
+        // Fixing the first illegal ']' on its own would need a second edit for the '>'
+        let repair = linter.repair("(]>");
+        assert_eq!(repair.edits.len(), 1);
+        assert_eq!(repair.line, "(<>");
+        assert_eq!(repair.completion, ")");
+
+        assert_eq!(linter.repair("((())").edits, vec![]);
+        // "((()[]" only needs two substitutions
+        assert_eq!(linter.repair("(((]]]").edits.len(), 2);
+        assert_eq!(
+            linter.repair("())").edits,
+            vec![Edit::Delete {
+                column: 3,
+                found: ')'
+            }]
+        );
+
+        let i = read_input_file("input/2021/day10_test.txt");
+        for line in i.lines() {
+            let repair = linter.repair(line);
+            let expected_edits = match linter.lint(line) {
+                LineStatus::Corrupted { .. } => 1,
+                _ => 0,
+            };
+
+            assert_eq!(repair.edits.len(), expected_edits, "{}", line);
+            assert_eq!(linter.lint(&repair.fixed_line()), LineStatus::Valid);
+        }
+    }
+
+    #[test]
+    fn custom_grammar_report() {
+        use super::{BracketLinter, BracketPair};
+
+        let linter = BracketLinter::new(
+            vec![BracketPair {
+                open: '«',
+                close: '»',
+                corrupted_score: 10,
+                completion_score: 1,
+            }],
+            2,
+        );
+        assert_eq!(linter.syntax_error_score(&linter.lint("«»»")), 10);
+        assert_eq!(linter.completion_score(&linter.lint("«««»")), Some(3));
+
+        assert_eq!(
+            linter.report("«»\n«»»\n««"),
+            "error: unexpected `»` with nothing left to close
+ --> line 2:3
+  |
+2 | «»»
+  |   ^ unexpected closer
+
+warning: incomplete line
+ --> line 3:3
+  |
+3 | ««
+  |   ^ missing `»»`
+
+"
+        );
+    }
+
+    #[test]
+    fn stream_chunk_boundaries() {
+        use super::{BracketLinter, BracketPair, StreamSummary};
+        use std::io::{BufReader, Read};
+
+        let linter = BracketLinter::new(
+            vec![BracketPair {
+                open: '«',
+                close: '»',
+                corrupted_score: 10,
+                completion_score: 1,
+            }],
+            2,
+        );
+        // A one byte buffer splits every multi-byte character across reads
+        let input = "««»\r\n»\n«««\n\n«»»»";
+        let summary = linter
+            .check_stream(BufReader::with_capacity(1, input.as_bytes()))
+            .unwrap();
+        assert_eq!(
+            summary,
+            StreamSummary {
+                syntax_error_score: 20,
+                completion_score: Some(7),
+            }
+        );
+
+        // A very deep line never gets built up in memory, and its score saturates instead of overflowing
+        let deep = std::io::repeat(b'(').take(1_000_000).chain(")]".as_bytes());
+        let summary = BracketLinter::navigation()
+            .check_stream(BufReader::new(deep))
+            .unwrap();
+        assert_eq!(summary.syntax_error_score, 57);
+        assert_eq!(summary.completion_score, None);
+
+        let deep = std::io::repeat(b'[').take(1_000_000);
+        let summary = BracketLinter::navigation()
+            .check_stream(BufReader::new(deep))
+            .unwrap();
+        assert_eq!(summary.completion_score, Some(usize::MAX));
+    }
 }
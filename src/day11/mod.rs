@@ -1,9 +1,11 @@
+use std::{fmt::Write as _, io::Write, ops::Range, time::Duration};
+
 use fnv::FnvHashMap;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Coordinate {
-    x: usize,
-    y: usize,
+    pub x: usize,
+    pub y: usize,
 }
 
 impl Coordinate {
@@ -112,6 +114,253 @@ impl OctopusSwarm {
     }
 }
 
+/// Octopuses after one step of `DenseSwarm`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepReport {
+    /// Every octopus that flashed, in the order the cascade reached them
+    pub flashes: Vec<Coordinate>,
+}
+
+impl StepReport {
+    pub fn flash_count(&self) -> usize {
+        self.flashes.len()
+    }
+}
+
+/// First repeated state of a `DenseSwarm`, the state after `start` steps shows up again every `length` steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationOptions {
+    /// Frames drawn per second, 0 draws them as fast as possible
+    pub frames_per_second: u32,
+    /// Which states to draw, step 0 is the swarm before any steps are taken
+    pub steps: Range<usize>,
+    /// Draw energy levels as ANSI shades with flashes highlighted, otherwise just the digits
+    pub color: bool,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        AnimationOptions {
+            frames_per_second: 10,
+            steps: 0..101,
+            color: true,
+        }
+    }
+}
+
+/// Which octopuses get charged by a flash, as offsets from the one flashing
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Neighborhood {
+    /// Up, down, left and right
+    VonNeumann,
+    /// All 8 surrounding octopuses, like the puzzle
+    Moore,
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Neighborhood {
+    fn offsets(&self) -> Vec<(isize, isize)> {
+        match self {
+            Neighborhood::VonNeumann => vec![(0, -1), (1, 0), (0, 1), (-1, 0)],
+            Neighborhood::Moore => (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                .filter(|&offset| offset != (0, 0))
+                .collect(),
+            Neighborhood::Custom(offsets) => offsets.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edges {
+    /// Neighbors past the edge don't exist
+    Bounded,
+    /// The grid is a torus, neighbors past one edge come from the opposite edge
+    Wrap,
+}
+
+/// Rules for the flash cascade of a `DenseSwarm`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rules {
+    /// An octopus flashes once its energy goes over this, so it has to be below `u8::MAX`
+    pub threshold: u8,
+    /// Energy of an octopus after it flashed
+    pub reset: u8,
+    pub neighborhood: Neighborhood,
+    pub edges: Edges,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            threshold: 9,
+            reset: 0,
+            neighborhood: Neighborhood::Moore,
+            edges: Edges::Bounded,
+        }
+    }
+}
+
+/// Swarm stored as a row major `Vec` of energy levels, flashes cascade through a work queue instead of
+/// rescanning the whole swarm until nothing is left to flash
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DenseSwarm {
+    energy: Vec<u8>,
+    width: usize,
+    height: usize,
+    rules: Rules,
+    offsets: Vec<(isize, isize)>,
+}
+
+impl DenseSwarm {
+    pub fn new(input: &(FnvHashMap<Coordinate, Octopus>, usize, usize)) -> Self {
+        DenseSwarm::with_rules(input, Rules::default())
+    }
+
+    pub fn with_rules(
+        input: &(FnvHashMap<Coordinate, Octopus>, usize, usize),
+        rules: Rules,
+    ) -> Self {
+        // Energy saturates at u8::MAX, so it could never go over a threshold of u8::MAX
+        assert!(
+            rules.threshold < u8::MAX,
+            "Flash threshold must be below {}",
+            u8::MAX
+        );
+
+        let width = input.1 + 1;
+        let height = input.2 + 1;
+        let mut energy = vec![0; width * height];
+
+        for (coord, octo) in &input.0 {
+            energy[coord.y * width + coord.x] = octo.energy as u8;
+        }
+
+        DenseSwarm {
+            energy,
+            width,
+            height,
+            offsets: rules.neighborhood.offsets(),
+            rules,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.energy.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.energy.is_empty()
+    }
+
+    fn coordinate(&self, idx: usize) -> Coordinate {
+        Coordinate::new(idx % self.width, idx / self.width)
+    }
+
+    fn neighbor(&self, idx: usize, (dx, dy): (isize, isize)) -> Option<usize> {
+        let x = (idx % self.width) as isize + dx;
+        let y = (idx / self.width) as isize + dy;
+        let (width, height) = (self.width as isize, self.height as isize);
+
+        match self.rules.edges {
+            Edges::Bounded if x < 0 || y < 0 || x >= width || y >= height => None,
+            Edges::Bounded => Some((y * width + x) as usize),
+            Edges::Wrap => Some((y.rem_euclid(height) * width + x.rem_euclid(width)) as usize),
+        }
+    }
+
+    pub fn step(&mut self) -> StepReport {
+        let threshold = self.rules.threshold;
+        let mut flashed = vec![false; self.len()];
+        let mut queue = Vec::new();
+        for (idx, energy) in self.energy.iter_mut().enumerate() {
+            *energy = energy.saturating_add(1);
+            if *energy > threshold {
+                flashed[idx] = true;
+                queue.push(idx);
+            }
+        }
+
+        // Everything gets queued exactly once, when it first goes over the threshold
+        let mut flashes = Vec::new();
+        while let Some(idx) = queue.pop() {
+            flashes.push(self.coordinate(idx));
+
+            for &offset in &self.offsets {
+                if let Some(neighbor) = self.neighbor(idx, offset) {
+                    if !flashed[neighbor] {
+                        self.energy[neighbor] = self.energy[neighbor].saturating_add(1);
+                        if self.energy[neighbor] > threshold {
+                            flashed[neighbor] = true;
+                            queue.push(neighbor);
+                        }
+                    }
+                }
+            }
+        }
+
+        for (energy, _) in self
+            .energy
+            .iter_mut()
+            .zip(flashed)
+            .filter(|(_, flashed)| *flashed)
+        {
+            *energy = self.rules.reset;
+        }
+
+        StepReport { flashes }
+    }
+
+    /// Reports for the next `steps` steps, without modifying this swarm
+    pub fn history(&self, steps: usize) -> Vec<StepReport> {
+        let mut swarm = self.clone();
+        (0..steps).map(|_| swarm.step()).collect()
+    }
+
+    /// The first step (1-based) where every octopus flashes, `None` if the swarm gets stuck in a cycle first
+    pub fn first_synchronized_step(&self) -> Option<usize> {
+        let mut swarm = self.clone();
+        let mut seen = FnvHashMap::default();
+
+        for step in 1.. {
+            seen.insert(swarm.energy.clone(), step - 1);
+            if swarm.step().flash_count() == swarm.len() {
+                return Some(step);
+            }
+            if seen.contains_key(&swarm.energy) {
+                return None;
+            }
+        }
+
+        unreachable!("The swarm has a finite number of states")
+    }
+
+    /// Find the first state that repeats, with the puzzle rules a synchronized swarm ends up in a cycle of length 10
+    pub fn find_cycle(&self) -> Cycle {
+        let mut swarm = self.clone();
+        let mut seen = FnvHashMap::default();
+
+        for step in 0.. {
+            if let Some(&start) = seen.get(&swarm.energy) {
+                return Cycle {
+                    start,
+                    length: step - start,
+                };
+            }
+            seen.insert(swarm.energy.clone(), step);
+            swarm.step();
+        }
+
+        unreachable!("The swarm has a finite number of states")
+    }
+}
+
 pub fn input_generator(input: &str) -> (FnvHashMap<Coordinate, Octopus>, usize, usize) {
     let mut max_x = 0;
     let mut max_y = 0;
@@ -173,9 +422,105 @@ pub fn part2(input: &(FnvHashMap<Coordinate, Octopus>, usize, usize)) -> usize {
     steps
 }
 
+impl DenseSwarm {
+    /// Draw the current energy levels, `flashes` are drawn highlighted when `color` is set
+    pub fn render_frame(&self, flashes: &[Coordinate], color: bool) -> String {
+        let mut flashed = vec![false; self.len()];
+        for coord in flashes {
+            flashed[coord.y * self.width + coord.x] = true;
+        }
+
+        let mut frame = String::with_capacity(self.len() * if color { 16 } else { 1 });
+        for (row_energy, row_flashed) in self
+            .energy
+            .chunks(self.width)
+            .zip(flashed.chunks(self.width))
+        {
+            for (&energy, &flashed) in row_energy.iter().zip(row_flashed) {
+                if !color {
+                    write!(frame, "{}", energy)
+                } else if flashed {
+                    // Bold bright yellow
+                    write!(frame, "\x1b[1;93m{}\x1b[0m", energy)
+                } else {
+                    // Grey ramp from the 256 color palette, brighter as the energy builds
+                    write!(
+                        frame,
+                        "\x1b[38;5;{}m{}\x1b[0m",
+                        236 + energy.min(9) * 2,
+                        energy
+                    )
+                }
+                .expect("Writing to a String can't fail");
+            }
+            frame.push('\n');
+        }
+
+        frame
+    }
+
+    /// Frames for each state in `steps`, with plain digits these match the puzzle's step listings
+    pub fn frames(&self, steps: Range<usize>, color: bool) -> Vec<String> {
+        let mut swarm = self.clone();
+        let mut flashes = Vec::new();
+        let mut frames = Vec::with_capacity(steps.len());
+
+        for step in 0..steps.end {
+            if step > 0 {
+                flashes = swarm.step().flashes;
+            }
+            if steps.contains(&step) {
+                frames.push(swarm.render_frame(&flashes, color));
+            }
+        }
+
+        frames
+    }
+
+    /// Play the swarm in a terminal, redrawing over the previous frame each step
+    pub fn animate<W: Write>(
+        &self,
+        writer: &mut W,
+        options: &AnimationOptions,
+    ) -> std::io::Result<()> {
+        let delay = match options.frames_per_second {
+            0 => Duration::ZERO,
+            fps => Duration::from_secs(1) / fps,
+        };
+
+        // Clear the screen once, then only move the cursor back to the top
+        write!(writer, "\x1b[2J")?;
+        for (step, frame) in options
+            .steps
+            .clone()
+            .zip(self.frames(options.steps.clone(), options.color))
+        {
+            write!(writer, "\x1b[H")?;
+            writeln!(writer, "Step {}", step)?;
+            write!(writer, "{}", frame)?;
+            writer.flush()?;
+            std::thread::sleep(delay);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn part1_dense(input: &(FnvHashMap<Coordinate, Octopus>, usize, usize)) -> usize {
+    let mut swarm = DenseSwarm::new(input);
+
+    (0..100).map(|_| swarm.step().flash_count()).sum()
+}
+
+pub fn part2_dense(input: &(FnvHashMap<Coordinate, Octopus>, usize, usize)) -> usize {
+    DenseSwarm::new(input)
+        .first_synchronized_step()
+        .expect("Swarm never synchronizes")
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -187,11 +532,150 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
 
     test!(part1, 1656);
     test!(part2, 195);
+    test!(part1_dense, 1656);
+    test!(part2_dense, 195);
+
+    #[test]
+    fn dense_history() {
+        use super::{Coordinate, Cycle, DenseSwarm};
+
+        let i = read_input_file("input/2021/day11_test.txt");
+        let swarm = DenseSwarm::new(&super::input_generator(&i));
+
+        let counts: Vec<usize> = swarm
+            .history(10)
+            .iter()
+            .map(|report| report.flash_count())
+            .collect();
+        assert_eq!(counts, vec![0, 35, 45, 16, 8, 1, 7, 24, 39, 29]);
+        assert_eq!(counts.iter().sum::<usize>(), 204);
+
+        // Every octopus is at 0 right after the synchronized step 195, from then on they flash together
+        assert_eq!(
+            swarm.find_cycle(),
+            Cycle {
+                start: 195,
+                length: 10
+            }
+        );
+
+        let small = DenseSwarm::new(&super::input_generator("11111\n19991\n19191\n19991\n11111"));
+        let mut flashes = small.history(1)[0].flashes.clone();
+        flashes.sort_by_key(|coord| (coord.y, coord.x));
+        assert_eq!(flashes.len(), 9);
+        assert_eq!(flashes[0], Coordinate { x: 1, y: 1 });
+        assert_eq!(flashes[4], Coordinate { x: 2, y: 2 });
+    }
+
+    #[test]
+    #[should_panic(expected = "Flash threshold must be below 255")]
+    fn unreachable_threshold() {
+        let rules = super::Rules {
+            threshold: u8::MAX,
+            ..super::Rules::default()
+        };
+        super::DenseSwarm::with_rules(&super::input_generator("12\n34"), rules);
+    }
+
+    #[test]
+    fn custom_rules() {
+        use super::{DenseSwarm, Edges, Neighborhood, Rules};
+
+        let swarm = |raw: &str, rules: Rules| {
+            DenseSwarm::with_rules(&super::input_generator(raw), rules).frames(1..2, false)[0]
+                .clone()
+        };
+
+        // Default rules are the puzzle's
+        let i = read_input_file("input/2021/day11_test.txt");
+        assert_eq!(
+            swarm(&i, Rules::default()),
+            DenseSwarm::new(&super::input_generator(&i)).frames(1..2, false)[0]
+        );
+
+        let von_neumann = Rules {
+            neighborhood: Neighborhood::VonNeumann,
+            edges: Edges::Wrap,
+            ..Rules::default()
+        };
+        assert_eq!(swarm("900\n000\n000", von_neumann), "022\n211\n211\n");
+
+        let right_only = |edges| Rules {
+            neighborhood: Neighborhood::Custom(vec![(1, 0)]),
+            edges,
+            ..Rules::default()
+        };
+        assert_eq!(swarm("0009", right_only(Edges::Bounded)), "1110\n");
+        assert_eq!(swarm("0009", right_only(Edges::Wrap)), "2110\n");
+
+        let low_threshold = Rules {
+            threshold: 3,
+            reset: 1,
+            ..Rules::default()
+        };
+        assert_eq!(swarm("300\n000", low_threshold), "121\n221\n");
+    }
+
+    #[test]
+    fn frame_snapshots() {
+        use super::{AnimationOptions, DenseSwarm};
+
+        let i = read_input_file("input/2021/day11_test.txt");
+        let swarm = DenseSwarm::new(&super::input_generator(&i));
+
+        let frames = swarm.frames(0..3, false);
+        assert_eq!(frames[0], format!("{}\n", i));
+        assert_eq!(
+            frames[1],
+            "6594254334
+3856965822
+6375667284
+7252447257
+7468496589
+5278635756
+3287952832
+7993992245
+5957959665
+6394862637
+"
+        );
+        assert_eq!(
+            frames[2],
+            "8807476555
+5089087054
+8597889608
+8485769600
+8700908800
+6600088989
+6800005943
+0000007456
+9000000876
+8700006848
+"
+        );
+
+        let mut out = Vec::new();
+        swarm
+            .animate(
+                &mut out,
+                &AnimationOptions {
+                    frames_per_second: 0,
+                    steps: 1..3,
+                    color: true,
+                },
+            )
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("\x1b[2J\x1b[HStep 1\n"));
+        assert!(out.contains(
+            "\x1b[HStep 2\n\x1b[38;5;252m8\x1b[0m\x1b[38;5;252m8\x1b[0m\x1b[1;93m0\x1b[0m"
+        ));
+    }
 }
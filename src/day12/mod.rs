@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use fnv::{FnvHashMap, FnvHashSet};
 
 #[derive(Debug, Clone)]
@@ -47,6 +49,105 @@ impl CaveSystem {
         unreachable!("Tried to get node that doesn't exist");
     }
 
+    /// Iterate over every path from `start` to `end`, optionally letting a single small cave be visited twice
+    pub fn paths(&self, allow_revisit: bool) -> PathIter<'_> {
+        let start = self
+            .system
+            .iter()
+            .find(|node| node.base == CaveNodeType::Start)
+            .map(|node| &node.base);
+
+        PathIter {
+            system: self,
+            allow_revisit,
+            path: start.into_iter().collect(),
+            next_connection: start.map(|_| 0).into_iter().collect(),
+            visits: FnvHashMap::default(),
+            revisited_at: None,
+        }
+    }
+
+    /// Every path formatted like `start,A,b,A,end`. Enumeration stops after `limit` paths when one is given,
+    /// and `sorted` orders whatever was collected.
+    pub fn list_paths(
+        &self,
+        allow_revisit: bool,
+        limit: Option<usize>,
+        sorted: bool,
+    ) -> Vec<String> {
+        let mut paths: Vec<String> = self
+            .paths(allow_revisit)
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|path| format_path(&path))
+            .collect();
+
+        if sorted {
+            paths.sort();
+        }
+
+        paths
+    }
+
+    /// Render the cave system as an undirected Graphviz graph, view it with `dot -Tsvg`. Caves and tunnels along
+    /// `highlight` are drawn in red, with each tunnel labelled by how many times the path uses it.
+    pub fn to_dot(&self, highlight: Option<&[&CaveNodeType]>) -> String {
+        let highlight = highlight.unwrap_or(&[]);
+        let mut traversals: FnvHashMap<(&CaveNodeType, &CaveNodeType), usize> =
+            FnvHashMap::default();
+        for step in highlight.windows(2) {
+            *traversals
+                .entry(Self::tunnel(step[0], step[1]))
+                .or_insert(0) += 1;
+        }
+
+        let mut dot = String::from("graph caves {\n");
+        for node in &self.system {
+            let style = match node.base {
+                CaveNodeType::Start => "shape=box, style=filled, fillcolor=palegreen",
+                CaveNodeType::End => "shape=box, style=filled, fillcolor=lightpink",
+                CaveNodeType::Small(_) => "shape=circle",
+                CaveNodeType::Large(_) => "shape=doublecircle, style=filled, fillcolor=lightblue",
+            };
+            let color = if highlight.contains(&&node.base) {
+                ", color=red, penwidth=2"
+            } else {
+                ""
+            };
+            dot += &format!("    \"{}\" [{}{}];\n", node.base, style, color);
+        }
+
+        let mut drawn = FnvHashSet::default();
+        for node in &self.system {
+            for conn in &node.connections {
+                let tunnel = Self::tunnel(&node.base, conn);
+                if !drawn.insert(tunnel) {
+                    continue;
+                }
+
+                let style = match traversals.get(&tunnel) {
+                    Some(count) => format!(" [color=red, penwidth=2, label=\"{}\"]", count),
+                    None => String::new(),
+                };
+                dot += &format!("    \"{}\" -- \"{}\"{};\n", tunnel.0, tunnel.1, style);
+            }
+        }
+        dot += "}\n";
+
+        dot
+    }
+
+    // Tunnels are undirected, so order the ends the same way whichever side they're walked from
+    fn tunnel<'a>(
+        left: &'a CaveNodeType,
+        right: &'a CaveNodeType,
+    ) -> (&'a CaveNodeType, &'a CaveNodeType) {
+        if left <= right {
+            (left, right)
+        } else {
+            (right, left)
+        }
+    }
+
     fn start_exploration(&self) -> usize {
         self.system
             .iter()
@@ -155,6 +256,305 @@ impl CaveSystem {
     }
 }
 
+/// Depth first walk over a `CaveSystem` yielding one complete path at a time
+#[derive(Debug, Clone)]
+pub struct PathIter<'a> {
+    system: &'a CaveSystem,
+    allow_revisit: bool,
+    path: Vec<&'a CaveNodeType>,
+    // Index of the next connection to try for each cave in `path`
+    next_connection: Vec<usize>,
+    visits: FnvHashMap<&'a CaveNodeType, usize>,
+    // Position in `path` of the small cave that used up the revisit
+    revisited_at: Option<usize>,
+}
+
+impl<'a> Iterator for PathIter<'a> {
+    type Item = Vec<&'a CaveNodeType>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let current = *self.path.last()?;
+            let node = self.system.get_node(current);
+            let next = self.next_connection.last_mut()?;
+
+            let conn = match node.connections.get(*next) {
+                Some(conn) => conn,
+                None => {
+                    // Every connection has been tried, so step back out of this cave
+                    self.path.pop();
+                    self.next_connection.pop();
+                    if let Some(visits) = self.visits.get_mut(current) {
+                        *visits -= 1;
+                    }
+                    if self.revisited_at == Some(self.path.len()) {
+                        self.revisited_at = None;
+                    }
+                    continue;
+                }
+            };
+            *next += 1;
+
+            match conn {
+                CaveNodeType::Start => continue,
+                CaveNodeType::End => {
+                    let mut path = self.path.clone();
+                    path.push(conn);
+                    return Some(path);
+                }
+                CaveNodeType::Large(_) => {}
+                CaveNodeType::Small(_) => {
+                    let visits = self.visits.entry(conn).or_insert(0);
+                    if *visits > 0 {
+                        if !self.allow_revisit || self.revisited_at.is_some() {
+                            continue;
+                        }
+                        self.revisited_at = Some(self.path.len());
+                    }
+                    *visits += 1;
+                }
+            }
+
+            self.path.push(conn);
+            self.next_connection.push(0);
+        }
+    }
+}
+
+/// `CaveSystem` with every cave interned to its index in `CaveSystem::system`, so a set of caves fits in a `u64`
+#[derive(Debug, Clone)]
+pub struct InternedCaves {
+    caves: Vec<CaveNodeType>,
+    adjacency: Vec<Vec<usize>>,
+    // Bit set for every cave that can't be entered more than once (without the revisit)
+    small: u64,
+    start: usize,
+    end: usize,
+}
+
+impl InternedCaves {
+    pub fn new(system: &CaveSystem) -> Self {
+        assert!(
+            system.system.len() <= 64,
+            "InternedCaves supports at most 64 caves"
+        );
+
+        let id = |cave: &CaveNodeType| {
+            system
+                .system
+                .iter()
+                .position(|node| &node.base == cave)
+                .expect("Connection to a cave that doesn't exist")
+        };
+
+        InternedCaves {
+            caves: system.system.iter().map(|node| node.base.clone()).collect(),
+            adjacency: system
+                .system
+                .iter()
+                .map(|node| node.connections.iter().map(id).collect())
+                .collect(),
+            small: system
+                .system
+                .iter()
+                .enumerate()
+                .filter(|(_, node)| !matches!(node.base, CaveNodeType::Large(_)))
+                .fold(0, |small, (idx, _)| small | 1 << idx),
+            start: id(&CaveNodeType::Start),
+            end: id(&CaveNodeType::End),
+        }
+    }
+
+    /// Count paths from `start` to `end`, memoized on (cave, visited small caves, revisit used) since the number
+    /// of ways to finish a path only depends on those
+    pub fn count_paths(&self, allow_revisit: bool) -> usize {
+        let mut memo = FnvHashMap::default();
+        self.count(self.start, 1 << self.start, !allow_revisit, &mut memo)
+    }
+
+    fn count(
+        &self,
+        cave: usize,
+        visited: u64,
+        revisit_used: bool,
+        memo: &mut FnvHashMap<(usize, u64, bool), usize>,
+    ) -> usize {
+        if cave == self.end {
+            return 1;
+        }
+        if let Some(&count) = memo.get(&(cave, visited, revisit_used)) {
+            return count;
+        }
+
+        let mut total = 0;
+        for &next in &self.adjacency[cave] {
+            let bit = 1 << next;
+
+            total += if next == self.start {
+                0
+            } else if self.small & bit == 0 {
+                self.count(next, visited, revisit_used, memo)
+            } else if visited & bit == 0 {
+                self.count(next, visited | bit, revisit_used, memo)
+            } else if !revisit_used {
+                self.count(next, visited, true, memo)
+            } else {
+                0
+            };
+        }
+
+        memo.insert((cave, visited, revisit_used), total);
+        total
+    }
+
+    /// Count paths from `start` to `end` where `policy` decides how often each cave may be entered. Caves without
+    /// a limit don't change the search state, so walking between two of them leads back to a state that is
+    /// still being counted, and if that state can reach `end` there are infinitely many paths.
+    pub fn count_paths_with(&self, policy: &VisitPolicy) -> PathCount {
+        let mut search = PolicySearch {
+            caves: self,
+            limits: self.caves.iter().map(|cave| policy.limit(cave)).collect(),
+            capped: self
+                .caves
+                .iter()
+                .map(|cave| policy.overrides.contains_key(cave))
+                .collect(),
+            memo: FnvHashMap::default(),
+            in_progress: FnvHashSet::default(),
+            looped: FnvHashSet::default(),
+            infinite: false,
+        };
+
+        let mut visits = vec![0; self.caves.len()];
+        visits[self.start] = 1;
+        let count = search.count(self.start, &mut visits, policy.revisit_budget);
+
+        if search.infinite {
+            PathCount::Infinite
+        } else {
+            PathCount::Finite(count)
+        }
+    }
+}
+
+/// How many times a path may enter each cave. `start` is never re-entered and reaching `end` finishes the path,
+/// so neither is affected by the policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisitPolicy {
+    /// Visits allowed for every small cave without an override
+    pub max_visits: usize,
+    /// Extra visits that may be spent across the whole path on small caves that already reached `max_visits`
+    pub revisit_budget: usize,
+    /// Hard limits for specific caves that the revisit budget can't raise, these can also restrict large caves
+    /// which are otherwise unlimited
+    pub overrides: FnvHashMap<CaveNodeType, usize>,
+}
+
+impl Default for VisitPolicy {
+    /// The rules from part 1, every small cave at most once
+    fn default() -> Self {
+        Self {
+            max_visits: 1,
+            revisit_budget: 0,
+            overrides: FnvHashMap::default(),
+        }
+    }
+}
+
+impl VisitPolicy {
+    /// The rules from part 2, a single small cave may be visited twice
+    pub fn single_revisit() -> Self {
+        Self {
+            revisit_budget: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Visit limit for `cave`, `None` when it can be entered any number of times
+    pub fn limit(&self, cave: &CaveNodeType) -> Option<usize> {
+        match (self.overrides.get(cave), cave) {
+            (Some(&limit), _) => Some(limit),
+            (None, CaveNodeType::Large(_)) => None,
+            (None, _) => Some(self.max_visits),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathCount {
+    Finite(usize),
+    Infinite,
+}
+
+struct PolicySearch<'a> {
+    caves: &'a InternedCaves,
+    limits: Vec<Option<usize>>,
+    // Caves with an override, the budget can't be spent on these
+    capped: Vec<bool>,
+    memo: FnvHashMap<(usize, Vec<usize>, usize), usize>,
+    // States on the current walk, reaching one of these again means the walk went around a loop
+    in_progress: FnvHashSet<(usize, Vec<usize>, usize)>,
+    looped: FnvHashSet<(usize, Vec<usize>, usize)>,
+    infinite: bool,
+}
+
+impl PolicySearch<'_> {
+    fn count(&mut self, cave: usize, visits: &mut Vec<usize>, budget: usize) -> usize {
+        if cave == self.caves.end {
+            return 1;
+        }
+
+        let key = (cave, visits.clone(), budget);
+        if let Some(&count) = self.memo.get(&key) {
+            return count;
+        }
+        if !self.in_progress.insert(key.clone()) {
+            self.looped.insert(key);
+            return 0;
+        }
+
+        let mut total = 0usize;
+        for &next in &self.caves.adjacency[cave] {
+            if next == self.caves.start {
+                continue;
+            }
+
+            total = total.saturating_add(match self.limits[next] {
+                None => self.count(next, visits, budget),
+                Some(limit) if visits[next] < limit => {
+                    visits[next] += 1;
+                    let count = self.count(next, visits, budget);
+                    visits[next] -= 1;
+                    count
+                }
+                Some(_) if budget > 0 && !self.capped[next] => {
+                    visits[next] += 1;
+                    let count = self.count(next, visits, budget - 1);
+                    visits[next] -= 1;
+                    count
+                }
+                Some(_) => 0,
+            });
+        }
+
+        self.in_progress.remove(&key);
+        // Every state around a loop can reach every other one, so a loop that can reach `end` never runs out
+        if self.looped.remove(&key) && total > 0 {
+            self.infinite = true;
+        }
+        self.memo.insert(key, total);
+        total
+    }
+}
+
+/// Join the cave names of a path with commas, like the puzzle's listings
+pub fn format_path(path: &[&CaveNodeType]) -> String {
+    path.iter()
+        .map(|cave| cave.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CaveNode {
     base: CaveNodeType,
@@ -180,7 +580,7 @@ impl CaveNode {
     // }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum CaveNodeType {
     Start,
     Small(String),
@@ -188,6 +588,16 @@ pub enum CaveNodeType {
     End,
 }
 
+impl Display for CaveNodeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaveNodeType::Start => write!(f, "start"),
+            CaveNodeType::End => write!(f, "end"),
+            CaveNodeType::Small(name) | CaveNodeType::Large(name) => write!(f, "{}", name),
+        }
+    }
+}
+
 impl From<&str> for CaveNodeType {
     fn from(input: &str) -> Self {
         match input {
@@ -235,9 +645,35 @@ pub fn part2(input: &CaveSystem) -> usize {
     input.start_exploration_with_revisit()
 }
 
+pub fn part1_memoized(input: &CaveSystem) -> usize {
+    InternedCaves::new(input).count_paths(false)
+}
+
+pub fn part2_memoized(input: &CaveSystem) -> usize {
+    InternedCaves::new(input).count_paths(true)
+}
+
+pub fn part1_policy(input: &CaveSystem) -> usize {
+    match InternedCaves::new(input).count_paths_with(&VisitPolicy::default()) {
+        PathCount::Finite(count) => count,
+        PathCount::Infinite => {
+            panic!("Two large caves are connected, there are infinitely many paths")
+        }
+    }
+}
+
+pub fn part2_policy(input: &CaveSystem) -> usize {
+    match InternedCaves::new(input).count_paths_with(&VisitPolicy::single_revisit()) {
+        PathCount::Finite(count) => count,
+        PathCount::Infinite => {
+            panic!("Two large caves are connected, there are infinitely many paths")
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -249,11 +685,152 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
 
     test!(part1, 10);
     test!(part2, 36);
+    test!(part1_memoized, 10);
+    test!(part2_memoized, 36);
+    test!(part1_policy, 10);
+    test!(part2_policy, 36);
+
+    #[test]
+    fn memoized_larger_examples() {
+        let slightly_larger = super::input_generator(
+            "dc-end\nHN-start\nstart-kj\ndc-start\ndc-HN\nLN-dc\nHN-end\nkj-sa\nkj-HN\nkj-dc",
+        );
+        assert_eq!(super::part1_memoized(&slightly_larger), 19);
+        assert_eq!(super::part2_memoized(&slightly_larger), 103);
+
+        let even_larger = super::input_generator(
+            "fs-end\nhe-DX\nfs-he\nstart-DX\npj-DX\nend-zg\nzg-sl\nzg-pj\npj-he\nRW-he\nfs-DX\npj-RW\nzg-RW\nstart-pj\nhe-WI\nzg-he\npj-fs\nstart-RW",
+        );
+        assert_eq!(super::part1_memoized(&even_larger), 226);
+        assert_eq!(super::part2_memoized(&even_larger), 3509);
+        assert_eq!(super::part2(&even_larger), 3509);
+    }
+
+    #[test]
+    fn visit_policies() {
+        use super::{CaveNodeType, InternedCaves, PathCount, VisitPolicy};
+
+        let i = read_input_file("input/2021/day12_test.txt");
+        let caves = InternedCaves::new(&super::input_generator(&i));
+
+        let two_revisits = VisitPolicy {
+            revisit_budget: 2,
+            ..VisitPolicy::default()
+        };
+        let two_each = VisitPolicy {
+            max_visits: 2,
+            ..VisitPolicy::default()
+        };
+        let mut no_b = VisitPolicy::single_revisit();
+        no_b.overrides.insert(CaveNodeType::from("b"), 0);
+        let mut b_once = VisitPolicy::single_revisit();
+        b_once.overrides.insert(CaveNodeType::from("b"), 1);
+
+        assert_eq!(
+            caves.count_paths_with(&two_revisits),
+            PathCount::Finite(101)
+        );
+        assert_eq!(caves.count_paths_with(&two_each), PathCount::Finite(54));
+        // Overrides are hard limits, the budget can still go to `c` and `d` but not to `b`
+        assert_eq!(caves.count_paths_with(&no_b), PathCount::Finite(3));
+        assert_eq!(caves.count_paths_with(&b_once), PathCount::Finite(16));
+
+        let mut no_large = VisitPolicy::default();
+        no_large.overrides.insert(CaveNodeType::from("A"), 0);
+        assert_eq!(caves.count_paths_with(&no_large), PathCount::Finite(1));
+    }
+
+    #[test]
+    fn adjacent_large_caves() {
+        use super::{CaveNodeType, InternedCaves, PathCount, VisitPolicy};
+
+        let looping = InternedCaves::new(&super::input_generator("start-A\nA-B\nB-end"));
+        assert_eq!(
+            looping.count_paths_with(&VisitPolicy::default()),
+            PathCount::Infinite
+        );
+
+        let mut limited = VisitPolicy::default();
+        limited.overrides.insert(CaveNodeType::from("B"), 2);
+        assert_eq!(looping.count_paths_with(&limited), PathCount::Finite(2));
+
+        // The loop between A and B never leads to `end`, so it doesn't add any paths
+        let dead_end = InternedCaves::new(&super::input_generator("start-A\nA-B\nstart-b\nb-end"));
+        assert_eq!(
+            dead_end.count_paths_with(&VisitPolicy::single_revisit()),
+            PathCount::Finite(1)
+        );
+    }
+
+    #[test]
+    fn dot_export() {
+        use super::CaveNodeType;
+
+        let input = super::input_generator("start-A\nA-b\nA-end\nb-end");
+        assert_eq!(
+            input.to_dot(None),
+            concat!(
+                "graph caves {\n",
+                "    \"start\" [shape=box, style=filled, fillcolor=palegreen];\n",
+                "    \"A\" [shape=doublecircle, style=filled, fillcolor=lightblue];\n",
+                "    \"b\" [shape=circle];\n",
+                "    \"end\" [shape=box, style=filled, fillcolor=lightpink];\n",
+                "    \"start\" -- \"A\";\n",
+                "    \"b\" -- \"A\";\n",
+                "    \"A\" -- \"end\";\n",
+                "    \"b\" -- \"end\";\n",
+                "}\n",
+            )
+        );
+
+        let path: Vec<CaveNodeType> = ["start", "A", "b", "A", "end"]
+            .into_iter()
+            .map(CaveNodeType::from)
+            .collect();
+        let path: Vec<&CaveNodeType> = path.iter().collect();
+        let dot = input.to_dot(Some(&path));
+
+        assert!(dot.contains("\"b\" [shape=circle, color=red, penwidth=2];"));
+        assert!(dot.contains("\"b\" -- \"A\" [color=red, penwidth=2, label=\"2\"];"));
+        assert!(dot.contains("\"A\" -- \"end\" [color=red, penwidth=2, label=\"1\"];"));
+        assert!(dot.contains("\"b\" -- \"end\";"));
+    }
+
+    #[test]
+    fn enumerate_paths() {
+        let i = read_input_file("input/2021/day12_test.txt");
+        let input = super::input_generator(&i);
+
+        assert_eq!(
+            input.list_paths(false, None, true),
+            vec![
+                "start,A,b,A,c,A,end",
+                "start,A,b,A,end",
+                "start,A,b,end",
+                "start,A,c,A,b,A,end",
+                "start,A,c,A,b,end",
+                "start,A,c,A,end",
+                "start,A,end",
+                "start,b,A,c,A,end",
+                "start,b,A,end",
+                "start,b,end",
+            ]
+        );
+
+        let with_revisit = input.list_paths(true, None, true);
+        assert_eq!(with_revisit.len(), 36);
+        assert!(with_revisit.contains(&"start,A,b,A,b,A,c,A,end".to_string()));
+        assert!(with_revisit.contains(&"start,b,d,b,A,c,A,end".to_string()));
+        assert!(!with_revisit.contains(&"start,b,A,b,A,b,end".to_string()));
+
+        assert_eq!(input.list_paths(true, Some(5), false).len(), 5);
+        assert_eq!(input.paths(false).count(), super::part1(&input));
+    }
 }
@@ -1,46 +1,197 @@
 use std::{collections::VecDeque, fmt::Display};
 
-use fnv::FnvHashSet;
+use fnv::{FnvHashMap, FnvHashSet};
+
+use crate::Answer;
 
 #[derive(Debug, Clone)]
 pub struct Origami {
     points: FnvHashSet<Coordinate>,
     folds: VecDeque<Fold>,
-    largest_x: usize,
-    largest_y: usize,
+    width: usize,
+    height: usize,
 }
 
 impl Origami {
-    fn fold(&mut self) {
-        let fold = self.folds.pop_front().expect("No more folds to execute!");
+    /// Fold along the next line, mirroring the dots past it back over the line. When the part being folded over
+    /// is larger than the rest of the sheet, its far edge becomes the new origin and every dot shifts to match.
+    /// The fold is left queued when it fails.
+    pub fn fold(&mut self) -> Result<Fold, FoldError> {
+        let fold = *self.folds.front().ok_or(FoldError::NoFoldsLeft)?;
+        let (loc, size) = match fold {
+            Fold::X(loc) => (loc, self.width),
+            Fold::Y(loc) => (loc, self.height),
+        };
+
+        if loc >= size {
+            return Err(FoldError::OutsideSheet { fold, size });
+        }
+
+        let mut on_line: Vec<Coordinate> = self
+            .points
+            .iter()
+            .filter(|coord| coord.along(fold) == loc)
+            .copied()
+            .collect();
+        if !on_line.is_empty() {
+            on_line.sort_unstable();
+            return Err(FoldError::PointsOnLine {
+                fold,
+                points: on_line,
+            });
+        }
+
+        self.folds.pop_front();
+        let folded_size = loc.max(size - 1 - loc);
+        let offset = folded_size - loc;
+
+        let second_points: Vec<Coordinate> = self
+            .points
+            .extract_if(|coord| coord.along(fold) > loc)
+            .collect();
+
+        if offset > 0 {
+            self.points = self
+                .points
+                .drain()
+                .map(|coord| coord.moved(fold, |value| value + offset))
+                .collect();
+        }
+        for point in second_points {
+            self.points
+                .insert(point.moved(fold, |value| 2 * loc + offset - value));
+        }
 
         match fold {
-            Fold::X(loc) => {
-                self.largest_x = loc;
-                let mut second_points: Vec<Coordinate> =
-                    self.points.drain_filter(|coord| coord.x > loc).collect();
+            Fold::X(_) => self.width = folded_size,
+            Fold::Y(_) => self.height = folded_size,
+        }
 
-                for point in &mut second_points {
-                    self.points.insert(*point.x_translate(loc));
-                }
-            }
-            Fold::Y(loc) => {
-                self.largest_y = loc;
-                let mut second_points: Vec<Coordinate> =
-                    self.points.drain_filter(|coord| coord.y > loc).collect();
+        Ok(fold)
+    }
+
+    /// The sheet after each of the remaining folds, in order
+    pub fn history(&self) -> Result<Vec<Origami>, FoldError> {
+        let mut sheet = self.clone();
+        let mut history = Vec::with_capacity(self.folds.len());
+
+        while !sheet.folds.is_empty() {
+            sheet.fold()?;
+            history.push(sheet.clone());
+        }
+
+        Ok(history)
+    }
+
+    pub fn points(&self) -> &FnvHashSet<Coordinate> {
+        &self.points
+    }
 
-                for point in &mut second_points {
-                    self.points.insert(*point.y_translate(loc));
+    pub fn remaining_folds(&self) -> &VecDeque<Fold> {
+        &self.folds
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Read the dots as a row of letters drawn from the top left corner of the sheet, with a blank column after
+    /// each one. Letters that aren't in `table` come out as `?`.
+    pub fn read_letters(&self, table: &LetterTable) -> String {
+        let width = match self.points.iter().map(|coord| coord.x).max() {
+            Some(max_x) => max_x + 1,
+            None => return String::new(),
+        };
+
+        let letters = width.div_ceil(LETTER_WIDTH + 1);
+        (0..letters)
+            .map(|letter| {
+                let left = letter * (LETTER_WIDTH + 1);
+                let mut pixels = 0;
+                for y in 0..LETTER_HEIGHT {
+                    for x in left..left + LETTER_WIDTH {
+                        pixels <<= 1;
+                        if self.points.contains(&Coordinate { x, y }) {
+                            pixels |= 1;
+                        }
+                    }
                 }
+
+                table.recognize(pixels).unwrap_or('?')
+            })
+            .collect()
+    }
+}
+
+pub const LETTER_WIDTH: usize = 4;
+pub const LETTER_HEIGHT: usize = 6;
+
+/// Capital letters as they're drawn by the folded paper, 4 dots wide and 6 tall with a blank column between
+/// each letter
+#[derive(Debug, Clone, Default)]
+pub struct LetterTable {
+    // Each letter packed row by row into the low 24 bits, top left dot in the highest bit
+    letters: FnvHashMap<u32, char>,
+}
+
+impl LetterTable {
+    pub fn standard() -> Self {
+        let mut table = Self::default();
+
+        table.add('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]);
+        table.add('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]);
+        table.add('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]);
+        table.add('E', ["####", "#...", "###.", "#...", "#...", "####"]);
+        table.add('F', ["####", "#...", "###.", "#...", "#...", "#..."]);
+        table.add('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]);
+        table.add('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]);
+        table.add('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]);
+        table.add('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]);
+        table.add('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]);
+        table.add('L', ["#...", "#...", "#...", "#...", "#...", "####"]);
+        table.add('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]);
+        table.add('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]);
+        table.add('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]);
+        table.add('S', [".###", "#...", "#...", ".##.", "...#", "###."]);
+        table.add('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]);
+        table.add('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]);
+
+        table
+    }
+
+    /// Add or replace a letter, drawn with `#` for dots and anything else for blanks
+    pub fn add(&mut self, letter: char, rows: [&str; LETTER_HEIGHT]) {
+        let mut pixels = 0;
+        for row in rows {
+            assert_eq!(
+                row.chars().count(),
+                LETTER_WIDTH,
+                "Letter rows must be {} wide",
+                LETTER_WIDTH
+            );
+
+            for c in row.chars() {
+                pixels = pixels << 1 | (c == '#') as u32;
             }
         }
+
+        self.letters.retain(|_, &mut existing| existing != letter);
+        self.letters.insert(pixels, letter);
+    }
+
+    pub fn recognize(&self, pixels: u32) -> Option<char> {
+        self.letters.get(&pixels).copied()
     }
 }
 
 impl Display for Origami {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for y in 0..self.largest_y {
-            for x in 0..self.largest_x {
+        for y in 0..self.height {
+            for x in 0..self.width {
                 if self.points.get(&Coordinate { x, y }).is_some() {
                     write!(f, "#")?;
                 } else {
@@ -53,30 +204,79 @@ impl Display for Origami {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Coordinate {
-    x: usize,
-    y: usize,
+    pub x: usize,
+    pub y: usize,
 }
 
 impl Coordinate {
-    fn x_translate(&mut self, x: usize) -> &Self {
-        self.x = (x as isize - (self.x as isize - x as isize)).abs() as usize;
-        self
+    /// The value of the coordinate that `fold` changes
+    fn along(&self, fold: Fold) -> usize {
+        match fold {
+            Fold::X(_) => self.x,
+            Fold::Y(_) => self.y,
+        }
     }
 
-    fn y_translate(&mut self, y: usize) -> &Self {
-        self.y = (y as isize - (self.y as isize - y as isize)).abs() as usize;
+    fn moved(mut self, fold: Fold, change: impl Fn(usize) -> usize) -> Self {
+        match fold {
+            Fold::X(_) => self.x = change(self.x),
+            Fold::Y(_) => self.y = change(self.y),
+        }
         self
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Fold {
     X(usize),
     Y(usize),
 }
 
+impl Display for Fold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fold::X(loc) => write!(f, "fold along x={}", loc),
+            Fold::Y(loc) => write!(f, "fold along y={}", loc),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FoldError {
+    NoFoldsLeft,
+    /// The fold line is on or past the edge of the sheet
+    OutsideSheet {
+        fold: Fold,
+        size: usize,
+    },
+    /// Dots never appear on a fold line, there's no side for them to end up on
+    PointsOnLine {
+        fold: Fold,
+        points: Vec<Coordinate>,
+    },
+}
+
+impl Display for FoldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FoldError::NoFoldsLeft => write!(f, "there are no folds left"),
+            FoldError::OutsideSheet { fold, size } => {
+                write!(f, "{} is outside of a sheet {} dots across", fold, size)
+            }
+            FoldError::PointsOnLine { fold, points } => write!(
+                f,
+                "{} goes through {} dot(s), the first at {},{}",
+                fold,
+                points.len(),
+                points[0].x,
+                points[0].y
+            ),
+        }
+    }
+}
+
 pub fn input_generator(input: &str) -> Origami {
     let mut input = input.split("\n\n");
 
@@ -132,35 +332,64 @@ pub fn input_generator(input: &str) -> Origami {
         })
         .collect();
 
+    // The sheet is at least big enough for every dot, and for the first fold in each direction to be down the middle
+    let size = |coord: fn(&Coordinate) -> usize, first_fold: Option<usize>| {
+        let dots = points.iter().map(coord).max().map_or(0, |max| max + 1);
+        dots.max(first_fold.map_or(0, |loc| 2 * loc + 1))
+    };
+    let width = size(
+        |coord| coord.x,
+        folds.iter().find_map(|fold| match fold {
+            Fold::X(loc) => Some(*loc),
+            Fold::Y(_) => None,
+        }),
+    );
+    let height = size(
+        |coord| coord.y,
+        folds.iter().find_map(|fold| match fold {
+            Fold::Y(loc) => Some(*loc),
+            Fold::X(_) => None,
+        }),
+    );
+
     Origami {
         points,
         folds,
-        largest_x: 0,
-        largest_y: 0,
+        width,
+        height,
     }
 }
 
 pub fn part1(input: &Origami) -> usize {
     let mut foldable_origami = input.clone();
 
-    foldable_origami.fold();
+    foldable_origami.fold().expect("Error folding the paper");
 
     foldable_origami.points.len()
 }
 
-pub fn part2(input: &Origami) -> usize {
+pub fn part2(input: &Origami) -> String {
     let mut foldable_origami = input.clone();
     while foldable_origami.folds.len() != 0 {
-        foldable_origami.fold();
+        foldable_origami.fold().expect("Error folding the paper");
     }
 
-    print!("{}", foldable_origami);
-    0
+    foldable_origami.read_letters(&LetterTable::standard())
+}
+
+/// The folded paper itself, for when the letters aren't in the `LetterTable`
+pub fn part2_grid(input: &Origami) -> Answer {
+    let mut foldable_origami = input.clone();
+    while !foldable_origami.folds.is_empty() {
+        foldable_origami.fold().expect("Error folding the paper");
+    }
+
+    Answer::grid(&foldable_origami.to_string())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -172,11 +401,124 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
 
     test!(part1, 17);
-    test!(part2, 0);
+    // The example folds into a square, which isn't a letter
+    test!(part2, "?");
+    test!(
+        part2_grid,
+        Answer::from(vec![
+            "#####".to_string(),
+            "#   #".to_string(),
+            "#   #".to_string(),
+            "#   #".to_string(),
+            "#####".to_string(),
+            "     ".to_string(),
+            "     ".to_string(),
+        ])
+    );
+
+    #[test]
+    fn fold_history() {
+        let i = read_input_file("input/2021/day13_test.txt");
+        let input = super::input_generator(&i);
+        let history = input.history().unwrap();
+
+        let sizes: Vec<(usize, usize, usize)> = history
+            .iter()
+            .map(|sheet| (sheet.points().len(), sheet.width(), sheet.height()))
+            .collect();
+        assert_eq!(sizes, vec![(17, 11, 7), (16, 5, 7)]);
+        assert!(history[1].remaining_folds().is_empty());
+        assert_eq!(input.remaining_folds().len(), 2);
+    }
+
+    #[test]
+    fn uneven_folds() {
+        use super::{Coordinate, Fold, FoldError};
+
+        // The right side is bigger, so its far edge becomes the new left edge
+        let mut sheet = super::input_generator("0,0\n9,0\n3,1\n\nfold along x=2\nfold along x=8");
+        assert_eq!(sheet.fold(), Ok(Fold::X(2)));
+        assert_eq!(sheet.to_string(), "#    # \n      #\n");
+        assert_eq!(
+            sheet.fold(),
+            Err(FoldError::OutsideSheet {
+                fold: Fold::X(8),
+                size: 7
+            })
+        );
+
+        let mut on_line = super::input_generator("2,0\n2,3\n1,1\n\nfold along x=2");
+        let error = on_line.fold().unwrap_err();
+        assert_eq!(
+            error,
+            FoldError::PointsOnLine {
+                fold: Fold::X(2),
+                points: vec![Coordinate { x: 2, y: 0 }, Coordinate { x: 2, y: 3 }]
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "fold along x=2 goes through 2 dot(s), the first at 2,0"
+        );
+        assert_eq!(on_line.remaining_folds().len(), 1);
+
+        on_line.folds.clear();
+        assert_eq!(on_line.fold(), Err(FoldError::NoFoldsLeft));
+    }
+
+    #[test]
+    fn read_letters() {
+        use super::LetterTable;
+
+        let sheet = |drawn: &[&str]| {
+            let dots: Vec<String> = drawn
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.chars()
+                        .enumerate()
+                        .filter(|&(_, c)| c == '#')
+                        .map(move |(x, _)| format!("{},{}", x, y))
+                })
+                .collect();
+            super::input_generator(&format!("{}\n\nfold along x=100", dots.join("\n")))
+        };
+
+        let input = sheet(&[
+            "###...##..#....###.",
+            "#..#.#..#.#.....#..",
+            "###..#..#.#.....#..",
+            "#..#.#..#.#.....#..",
+            "#..#.#..#.#.....#..",
+            "###...##..####..#..",
+        ]);
+
+        let mut table = LetterTable::standard();
+        assert_eq!(input.read_letters(&table), "BOL?");
+
+        table.add('T', ["###.", ".#..", ".#..", ".#..", ".#..", ".#.."]);
+        assert_eq!(input.read_letters(&table), "BOLT");
+
+        // Drawing the same dots again replaces the old letter
+        table.add('D', ["###.", "#..#", "###.", "#..#", "#..#", "###."]);
+        assert_eq!(input.read_letters(&table), "DOLT");
+        assert_eq!(table.recognize(0), None);
+
+        // `I` has a blank first column, so the letters have to be found from the corner of the sheet
+        let leading_blank = sheet(&[
+            ".###.###.",
+            "..#..#..#",
+            "..#..###.",
+            "..#..#..#",
+            "..#..#..#",
+            ".###.###.",
+        ]);
+        assert_eq!(leading_blank.read_letters(&LetterTable::standard()), "IB");
+    }
 }
@@ -6,6 +6,111 @@ pub struct Polymerization<'a> {
     rules: FnvHashMap<&'a [u8], &'a [u8]>,
 }
 
+impl<'a> Polymerization<'a> {
+    /// Element and pair counts of the template before any insertions
+    pub fn template_counts(&self) -> Histogram {
+        let mut counts = Histogram::default();
+
+        for &element in self.polymer_template.as_bytes() {
+            *counts.elements.entry(element).or_insert(0) += 1;
+        }
+        for pair in self.polymer_template.as_bytes().windows(2) {
+            *counts.pairs.entry([pair[0], pair[1]]).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    /// Counts of the polymer after `steps` rounds of pair insertion
+    pub fn counts_after(&self, steps: usize) -> Histogram {
+        (0..steps).fold(self.template_counts(), |counts, _| self.step(&counts))
+    }
+
+    /// Counts after every step, starting with step 1
+    pub fn steps(&self) -> PolymerSteps<'_, 'a> {
+        PolymerSteps {
+            polymerization: self,
+            counts: self.template_counts(),
+        }
+    }
+
+    // Only the pairs matter for the next step, every `AB -> C` turns into `AC` and `CB` and adds one `C`
+    fn step(&self, counts: &Histogram) -> Histogram {
+        let mut next = Histogram {
+            elements: counts.elements.clone(),
+            pairs: FnvHashMap::default(),
+        };
+
+        for (pair, &count) in &counts.pairs {
+            let insertion = self
+                .rules
+                .get(&pair[..])
+                .expect("Rule does not exist in Polymerization structure")[0];
+
+            *next.elements.entry(insertion).or_insert(0) += count;
+            *next.pairs.entry([pair[0], insertion]).or_insert(0) += count;
+            *next.pairs.entry([insertion, pair[1]]).or_insert(0) += count;
+        }
+
+        next
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    pub elements: FnvHashMap<u8, usize>,
+    /// Adjacent elements, each element other than the first and last is part of two pairs
+    pub pairs: FnvHashMap<[u8; 2], usize>,
+}
+
+impl Histogram {
+    pub fn len(&self) -> usize {
+        self.elements.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn most_common(&self) -> Option<(u8, usize)> {
+        self.elements
+            .iter()
+            .max_by_key(|&(&element, &count)| (count, element))
+            .map(|(&element, &count)| (element, count))
+    }
+
+    pub fn least_common(&self) -> Option<(u8, usize)> {
+        self.elements
+            .iter()
+            .min_by_key(|&(&element, &count)| (count, element))
+            .map(|(&element, &count)| (element, count))
+    }
+
+    /// Count of the most common element minus the count of the least common one
+    pub fn spread(&self) -> usize {
+        match (self.most_common(), self.least_common()) {
+            (Some((_, max)), Some((_, min))) => max - min,
+            _ => 0,
+        }
+    }
+}
+
+/// Infinite iterator over the counts after each step of pair insertion
+#[derive(Debug, Clone)]
+pub struct PolymerSteps<'p, 'a> {
+    polymerization: &'p Polymerization<'a>,
+    counts: Histogram,
+}
+
+impl Iterator for PolymerSteps<'_, '_> {
+    type Item = Histogram;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.counts = self.polymerization.step(&self.counts);
+        Some(self.counts.clone())
+    }
+}
+
 pub fn input_generator(input: &str) -> Polymerization {
     let mut split_input = input.split("\n\n");
 
@@ -35,73 +140,7 @@ pub fn input_generator(input: &str) -> Polymerization {
 }
 
 pub fn part1(input: &Polymerization) -> usize {
-    let mut poly_chain_map_old: FnvHashMap<Vec<u8>, usize> = FnvHashMap::default();
-    let mut poly_chain_map_new: FnvHashMap<Vec<u8>, usize> = FnvHashMap::default();
-
-    input
-        .polymer_template
-        .as_bytes()
-        .windows(2)
-        .for_each(|byte_slice| {
-            *poly_chain_map_old.entry(byte_slice.to_owned()).or_insert(0) += 1;
-        });
-
-    for _ in 0..10 {
-        poly_chain_map_old.iter().for_each(|(byte_slice, &count)| {
-            let insertion = input
-                .rules
-                .get(byte_slice.as_slice())
-                .expect("Rule does not exist in Polymerization structure");
-
-            *poly_chain_map_new
-                .entry([byte_slice[0], insertion[0]].to_vec())
-                .or_insert(0) += count;
-            *poly_chain_map_new
-                .entry([insertion[0], byte_slice[1]].to_vec())
-                .or_insert(0) += count;
-        });
-
-        poly_chain_map_old = poly_chain_map_new.clone();
-        poly_chain_map_new.clear();
-    }
-
-    let mut char_map = FnvHashMap::default();
-    let first_byte = input
-        .polymer_template
-        .bytes()
-        .next()
-        .expect("Couldn't get first byte of polymer_template")
-        .to_owned();
-    char_map.insert(&first_byte, 1);
-    poly_chain_map_old.iter().for_each(|(key, val)| {
-        key.iter().skip(1).for_each(|letter| {
-            *char_map.entry(letter).or_insert(0) += val;
-        })
-    });
-
-    let mut max = None;
-    let mut min = None;
-
-    char_map.values().for_each(|val| {
-        if let Some(curr_max) = max {
-            if val > curr_max {
-                max = Some(val);
-            }
-        } else {
-            max = Some(val);
-        }
-
-        if let Some(curr_min) = min {
-            if val < curr_min {
-                min = Some(val);
-            }
-        } else {
-            min = Some(val);
-        }
-    });
-
-    max.expect("Max was never populated with Some!")
-        - min.expect("Min was never populated with Some!")
+    input.counts_after(10).spread()
 }
 
 pub fn part1_slow(input: &Polymerization) -> usize {
@@ -156,78 +195,12 @@ pub fn part1_slow(input: &Polymerization) -> usize {
 }
 
 pub fn part2(input: &Polymerization) -> usize {
-    let mut poly_chain_map_old: FnvHashMap<Vec<u8>, usize> = FnvHashMap::default();
-    let mut poly_chain_map_new: FnvHashMap<Vec<u8>, usize> = FnvHashMap::default();
-
-    input
-        .polymer_template
-        .as_bytes()
-        .windows(2)
-        .for_each(|byte_slice| {
-            *poly_chain_map_old.entry(byte_slice.to_owned()).or_insert(0) += 1;
-        });
-
-    for _ in 0..40 {
-        poly_chain_map_old.iter().for_each(|(byte_slice, &count)| {
-            let insertion = input
-                .rules
-                .get(byte_slice.as_slice())
-                .expect("Rule does not exist in Polymerization structure");
-
-            *poly_chain_map_new
-                .entry([byte_slice[0], insertion[0]].to_vec())
-                .or_insert(0) += count;
-            *poly_chain_map_new
-                .entry([insertion[0], byte_slice[1]].to_vec())
-                .or_insert(0) += count;
-        });
-
-        poly_chain_map_old = poly_chain_map_new.clone();
-        poly_chain_map_new.clear();
-    }
-
-    let mut char_map = FnvHashMap::default();
-    let first_byte = input
-        .polymer_template
-        .bytes()
-        .next()
-        .expect("Couldn't get first byte of polymer_template")
-        .to_owned();
-    char_map.insert(&first_byte, 1);
-    poly_chain_map_old.iter().for_each(|(key, val)| {
-        key.iter().skip(1).for_each(|letter| {
-            *char_map.entry(letter).or_insert(0) += val;
-        })
-    });
-
-    let mut max = None;
-    let mut min = None;
-
-    char_map.values().for_each(|val| {
-        if let Some(curr_max) = max {
-            if val > curr_max {
-                max = Some(val);
-            }
-        } else {
-            max = Some(val);
-        }
-
-        if let Some(curr_min) = min {
-            if val < curr_min {
-                min = Some(val);
-            }
-        } else {
-            min = Some(val);
-        }
-    });
-
-    max.expect("Max was never populated with Some!")
-        - min.expect("Min was never populated with Some!")
+    input.counts_after(40).spread()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -239,12 +212,40 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
 
     test!(part1, 1588);
     test!(part1_slow, 1588);
-    test!(part2, 2188189693529);
+    test!(part2, 2188189693529usize);
+
+    #[test]
+    fn step_histograms() {
+        let i = read_input_file("input/2021/day14_test.txt");
+        let input = super::input_generator(&i);
+
+        let lengths: Vec<usize> = input.steps().take(5).map(|counts| counts.len()).collect();
+        assert_eq!(lengths, vec![7, 13, 25, 49, 97]);
+
+        // NNCB -> NCNBCHB
+        let first = input.steps().next().unwrap();
+        assert_eq!(first, input.counts_after(1));
+        assert_eq!(first.elements[&b'N'], 2);
+        assert_eq!(first.elements[&b'H'], 1);
+        assert_eq!(first.pairs.len(), 6);
+        assert_eq!(first.pairs[b"CH"], 1);
+
+        let tenth = input.counts_after(10);
+        assert_eq!(tenth.len(), 3073);
+        assert_eq!(tenth.most_common(), Some((b'B', 1749)));
+        assert_eq!(tenth.least_common(), Some((b'H', 161)));
+        assert_eq!(tenth.elements[&b'C'], 298);
+        assert_eq!(tenth.elements[&b'N'], 865);
+        assert_eq!(tenth.pairs.values().sum::<usize>(), 3072);
+
+        assert_eq!(input.counts_after(0), input.template_counts());
+        assert_eq!(super::Histogram::default().spread(), 0);
+    }
 }
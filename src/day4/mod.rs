@@ -297,7 +297,7 @@ pub fn part2(input: &mut Bingo) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -309,7 +309,7 @@ mod tests {
                 ));
 
                 let mut input = super::input_generator(&i);
-                assert_eq!(super::$func(&mut input), $val);
+                assert_eq!(Answer::from(super::$func(&mut input)), Answer::from($val));
             }
         };
     }
@@ -1,3 +1,5 @@
+use std::{fmt::Display, io::Write};
+
 use fnv::FnvHashMap;
 
 #[derive(Default, Debug, Clone, Eq, PartialEq, Hash)]
@@ -80,6 +82,113 @@ impl Line {
 
         self.slope = Some(Slope::Diagonal(dir));
     }
+
+    /// Every point covered by the line, from `start` to `end` inclusive
+    fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        let dx = (self.end.x - self.start.x).signum();
+        let dy = (self.end.y - self.start.y).signum();
+        let len = (self.end.x - self.start.x)
+            .abs()
+            .max((self.end.y - self.start.y).abs());
+
+        (0..=len).map(move |step| Point {
+            x: self.start.x + dx * step,
+            y: self.start.y + dy * step,
+        })
+    }
+}
+
+/// Overlap counts of every vent line, used for rendering the board as ASCII (via `Display`) or as an image
+#[derive(Debug, Clone, Default)]
+pub struct VentBoard {
+    overlaps: FnvHashMap<Point, usize>,
+    min: Point,
+    max: Point,
+}
+
+impl VentBoard {
+    pub fn new(input: &[Line], include_diagonals: bool) -> Self {
+        let mut board = VentBoard::default();
+
+        for line in input {
+            if let Some(Slope::Diagonal(_)) = line.slope {
+                if !include_diagonals {
+                    continue;
+                }
+            }
+
+            for point in line.points() {
+                board.min.x = board.min.x.min(point.x);
+                board.min.y = board.min.y.min(point.y);
+                board.max.x = board.max.x.max(point.x);
+                board.max.y = board.max.y.max(point.y);
+                *board.overlaps.entry(point).or_insert(0) += 1;
+            }
+        }
+
+        board
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max.x - self.min.x + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max.y - self.min.y + 1) as usize
+    }
+
+    pub fn max_overlap(&self) -> usize {
+        self.overlaps.values().copied().max().unwrap_or(0)
+    }
+
+    pub fn overlap_at(&self, x: isize, y: isize) -> usize {
+        self.overlaps.get(&Point { x, y }).copied().unwrap_or(0)
+    }
+
+    /// Map each cell to 0..=255, scaled against the most overlapped cell on the board
+    fn intensities(&self) -> impl Iterator<Item = u8> + '_ {
+        let max = self.max_overlap().max(1);
+
+        (self.min.y..=self.max.y).flat_map(move |y| {
+            (self.min.x..=self.max.x).map(move |x| (self.overlap_at(x, y) * 255 / max) as u8)
+        })
+    }
+
+    /// Write the board as a binary greyscale PGM (P5)
+    pub fn write_pgm<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        write!(writer, "P5\n{} {}\n255\n", self.width(), self.height())?;
+        let pixels: Vec<u8> = self.intensities().collect();
+        writer.write_all(&pixels)
+    }
+
+    /// Write the board as a binary PPM (P6), untouched cells are black and overlaps go from blue to red
+    pub fn write_ppm<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width(), self.height())?;
+        let pixels: Vec<u8> = self
+            .intensities()
+            .flat_map(|i| match i {
+                0 => [0, 0, 0],
+                i => [i, 0, 255 - i],
+            })
+            .collect();
+        writer.write_all(&pixels)
+    }
+}
+
+impl Display for VentBoard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in self.min.y..=self.max.y {
+            for x in self.min.x..=self.max.x {
+                match self.overlap_at(x, y) {
+                    0 => write!(f, ".")?,
+                    count @ 1..=9 => write!(f, "{}", count)?,
+                    _ => write!(f, "#")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
 }
 
 pub fn input_generator(input: &str) -> Vec<Line> {
@@ -268,7 +377,7 @@ pub fn part2(input: &[Line]) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -280,11 +389,45 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
 
     test!(part1, 5);
     test!(part2, 12);
+
+    #[test]
+    fn vent_board_ascii() {
+        let i = read_input_file("input/2021/day5_test.txt");
+        let input = super::input_generator(&i);
+
+        let straight = super::VentBoard::new(&input, false);
+        assert_eq!(
+            straight.to_string(),
+            ".......1..\n..1....1..\n..1....1..\n.......1..\n.112111211\n..........\n..........\n..........\n..........\n222111....\n"
+        );
+
+        let all = super::VentBoard::new(&input, true);
+        assert_eq!(
+            all.to_string(),
+            "1.1....11.\n.111...2..\n..2.1.111.\n...1.2.2..\n.112313211\n...1.2....\n..1...1...\n.1.....1..\n1.......1.\n222111....\n"
+        );
+    }
+
+    #[test]
+    fn vent_board_pgm() {
+        let i = read_input_file("input/2021/day5_test.txt");
+        let board = super::VentBoard::new(&super::input_generator(&i), true);
+
+        let mut pgm = Vec::new();
+        board.write_pgm(&mut pgm).unwrap();
+
+        let header = b"P5\n10 10\n255\n";
+        assert_eq!(&pgm[..header.len()], header);
+        assert_eq!(pgm.len(), header.len() + 100);
+        // (4,4) is the only cell with 3 overlaps
+        assert_eq!(pgm[header.len() + 4 * 10 + 4], 255);
+        assert_eq!(pgm[header.len() + 1], 0);
+    }
 }
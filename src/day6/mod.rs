@@ -1,42 +1,67 @@
 use fnv::FnvHashMap;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct FishSchool {
     // This is a map of the days a fish has to reproduce and the number of fish in that stage
     school: FnvHashMap<usize, usize>,
+    // Days between a fish reproducing, the timer resets to `cycle_length - 1`
+    cycle_length: usize,
+    // Extra days a newborn fish waits on top of `cycle_length` before its first cycle
+    newborn_delay: usize,
 }
 
 impl FishSchool {
-    fn new(input: &[usize]) -> Self {
+    pub fn new(input: &[usize]) -> Self {
+        FishSchool::with_lifecycle(input, 7, 2)
+    }
+
+    pub fn with_lifecycle(input: &[usize], cycle_length: usize, newborn_delay: usize) -> Self {
+        assert!(cycle_length > 0, "A fish cycle must be at least one day");
+
         let mut school = FishSchool {
             school: FnvHashMap::default(),
+            cycle_length,
+            newborn_delay,
         };
 
-        // We know we only can have 9 states (0 to 8) so make them all now
-        for x in 0..=8 {
+        // We can only have `cycle_length + newborn_delay` states, so make them all now
+        for x in 0..=school.newborn_timer() {
             school.school.insert(x, 0);
         }
 
         for ea in input {
-            school.school.entry(*ea).and_modify(|val| *val += 1);
+            school
+                .school
+                .get_mut(ea)
+                .map(|val| *val += 1)
+                .expect("Fish timer is longer than the configured lifecycle");
         }
 
         school
     }
 
-    fn simulate_day(&mut self) {
+    fn reset_timer(&self) -> usize {
+        self.cycle_length - 1
+    }
+
+    fn newborn_timer(&self) -> usize {
+        self.cycle_length + self.newborn_delay - 1
+    }
+
+    pub fn simulate_day(&mut self) {
         let reproducing_fish = self
             .school
             .get(&0)
             .expect("Error while getting reproducing fish count")
             .to_owned();
 
+        let newborn_timer = self.newborn_timer();
         let mut prev_age_count = self
             .school
-            .get(&8)
+            .get(&newborn_timer)
             .expect("Error getting previous age count for fish")
             .to_owned();
-        for x in (0..=7).rev() {
+        for x in (0..newborn_timer).rev() {
             prev_age_count = self
                 .school
                 .insert(x, prev_age_count)
@@ -44,16 +69,218 @@ impl FishSchool {
         }
 
         self.school
-            .entry(8)
+            .entry(newborn_timer)
             .and_modify(|val| *val = reproducing_fish);
+        let reset_timer = self.reset_timer();
         self.school
-            .entry(6)
+            .entry(reset_timer)
             .and_modify(|val| *val += reproducing_fish);
     }
 
-    fn count_fish(&self) -> usize {
+    pub fn count_fish(&self) -> usize {
         self.school.values().sum()
     }
+
+    /// Population after `days` have passed, without modifying the current school
+    pub fn population_after(&self, days: usize) -> usize {
+        let mut school = self.clone();
+
+        for _ in 0..days {
+            school.simulate_day();
+        }
+
+        school.count_fish()
+    }
+
+    /// Population at the start and after each of the next `days` days, so the returned `Vec` has `days + 1` entries
+    pub fn population_history(&self, days: usize) -> Vec<usize> {
+        let mut school = self.clone();
+        let mut history = Vec::with_capacity(days + 1);
+        history.push(school.count_fish());
+
+        for _ in 0..days {
+            school.simulate_day();
+            history.push(school.count_fish());
+        }
+
+        history
+    }
+}
+
+/// Integer backend used by `TransitionMatrix`, every operation reports overflow by returning `None`
+pub trait FishArithmetic {
+    type Value: Copy;
+
+    fn value_of(&self, count: usize) -> Option<Self::Value>;
+    fn add(&self, a: Self::Value, b: Self::Value) -> Option<Self::Value>;
+    fn mul(&self, a: Self::Value, b: Self::Value) -> Option<Self::Value>;
+
+    fn zero(&self) -> Self::Value {
+        self.value_of(0).expect("Zero must be representable")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CheckedU64;
+
+impl FishArithmetic for CheckedU64 {
+    type Value = u64;
+
+    fn value_of(&self, count: usize) -> Option<u64> {
+        u64::try_from(count).ok()
+    }
+
+    fn add(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_add(b)
+    }
+
+    fn mul(&self, a: u64, b: u64) -> Option<u64> {
+        a.checked_mul(b)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CheckedU128;
+
+impl FishArithmetic for CheckedU128 {
+    type Value = u128;
+
+    fn value_of(&self, count: usize) -> Option<u128> {
+        u128::try_from(count).ok()
+    }
+
+    fn add(&self, a: u128, b: u128) -> Option<u128> {
+        a.checked_add(b)
+    }
+
+    fn mul(&self, a: u128, b: u128) -> Option<u128> {
+        a.checked_mul(b)
+    }
+}
+
+/// Arithmetic modulo the contained value, this never overflows
+#[derive(Debug, Clone, Copy)]
+pub struct Modular(u64);
+
+impl Modular {
+    pub fn new(modulus: u64) -> Self {
+        assert!(modulus != 0, "Modulus must not be 0");
+        Modular(modulus)
+    }
+
+    pub fn modulus(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FishArithmetic for Modular {
+    type Value = u64;
+
+    fn value_of(&self, count: usize) -> Option<u64> {
+        Some((count as u128 % self.0 as u128) as u64)
+    }
+
+    fn add(&self, a: u64, b: u64) -> Option<u64> {
+        Some(((a as u128 + b as u128) % self.0 as u128) as u64)
+    }
+
+    fn mul(&self, a: u64, b: u64) -> Option<u64> {
+        Some(((a as u128 * b as u128) % self.0 as u128) as u64)
+    }
+}
+
+/// Square matrix describing how one day moves fish between timer states, `cells[to][from]`
+#[derive(Debug, Clone)]
+pub struct TransitionMatrix<V> {
+    cells: Vec<Vec<V>>,
+}
+
+impl<V: Copy> TransitionMatrix<V> {
+    pub fn new<A: FishArithmetic<Value = V>>(school: &FishSchool, backend: &A) -> Self {
+        let states = school.newborn_timer() + 1;
+        let mut counts = vec![vec![0usize; states]; states];
+
+        for timer in 1..states {
+            counts[timer - 1][timer] = 1;
+        }
+        counts[school.newborn_timer()][0] += 1;
+        counts[school.reset_timer()][0] += 1;
+
+        TransitionMatrix {
+            cells: counts
+                .into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|count| backend.value_of(count).expect("0, 1 or 2 must fit"))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    fn multiply<A: FishArithmetic<Value = V>>(&self, other: &Self, backend: &A) -> Option<Self> {
+        let size = self.cells.len();
+        let mut cells = vec![vec![backend.zero(); size]; size];
+
+        for (row, cell_row) in cells.iter_mut().enumerate() {
+            for (col, cell) in cell_row.iter_mut().enumerate() {
+                for k in 0..size {
+                    let product = backend.mul(self.cells[row][k], other.cells[k][col])?;
+                    *cell = backend.add(*cell, product)?;
+                }
+            }
+        }
+
+        Some(TransitionMatrix { cells })
+    }
+
+    fn apply<A: FishArithmetic<Value = V>>(&self, vector: &[V], backend: &A) -> Option<Vec<V>> {
+        self.cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(vector)
+                    .try_fold(backend.zero(), |acc, (&m, &v)| {
+                        backend.add(acc, backend.mul(m, v)?)
+                    })
+            })
+            .collect()
+    }
+}
+
+impl FishSchool {
+    /// Population after `days` using exponentiation by squaring of the daily `TransitionMatrix`, which is O(log days).
+    /// Returns `None` if any intermediate value overflows the chosen backend, this includes the matrix powers so
+    /// it can happen even when the population itself would fit. An empty school is always `Some(zero)`.
+    pub fn population_after_matrix<A: FishArithmetic>(
+        &self,
+        days: u64,
+        backend: &A,
+    ) -> Option<A::Value> {
+        if self.count_fish() == 0 {
+            return Some(backend.zero());
+        }
+
+        let mut state = (0..=self.newborn_timer())
+            .map(|timer| backend.value_of(self.school[&timer]))
+            .collect::<Option<Vec<A::Value>>>()?;
+        let mut power = TransitionMatrix::new(self, backend);
+        let mut remaining = days;
+
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                state = power.apply(&state, backend)?;
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                power = power.multiply(&power, backend)?;
+            }
+        }
+
+        state
+            .into_iter()
+            .try_fold(backend.zero(), |acc, count| backend.add(acc, count))
+    }
 }
 
 pub fn input_generator(input: &str) -> Vec<usize> {
@@ -65,28 +292,22 @@ pub fn input_generator(input: &str) -> Vec<usize> {
 }
 
 pub fn part1(input: &[usize]) -> usize {
-    let mut school_of_fish = FishSchool::new(input);
-
-    for _ in 0..80 {
-        school_of_fish.simulate_day();
-    }
-
-    school_of_fish.count_fish()
+    FishSchool::new(input).population_after(80)
 }
 
 pub fn part2(input: &[usize]) -> usize {
-    let mut school_of_fish = FishSchool::new(input);
-
-    for _ in 0..256 {
-        school_of_fish.simulate_day();
-    }
+    FishSchool::new(input).population_after(256)
+}
 
-    school_of_fish.count_fish()
+pub fn part2_matrix(input: &[usize]) -> usize {
+    FishSchool::new(input)
+        .population_after_matrix(256, &CheckedU64)
+        .expect("Fish population overflowed a u64") as usize
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -98,11 +319,88 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
 
     test!(part1, 5934);
-    test!(part2, 26984457539);
+    test!(part2, 26984457539usize);
+    test!(part2_matrix, 26984457539usize);
+
+    #[test]
+    fn population_history() {
+        let i = read_input_file("input/2021/day6_test.txt");
+        let school = super::FishSchool::new(&super::input_generator(&i));
+
+        assert_eq!(school.population_history(5), vec![5, 5, 6, 7, 9, 10]);
+        assert_eq!(school.population_after(18), 26);
+    }
+
+    #[test]
+    fn custom_lifecycle() {
+        // Every fish reproduces every 3 days and newborns are immediately in sync with their parent
+        let school = super::FishSchool::with_lifecycle(&[0], 3, 0);
+
+        assert_eq!(school.population_history(7), vec![1, 2, 2, 2, 4, 4, 4, 8]);
+        assert_eq!(
+            school.population_after_matrix(7, &super::CheckedU64),
+            Some(8)
+        );
+    }
+
+    #[test]
+    fn matrix_backends() {
+        let i = read_input_file("input/2021/day6_test.txt");
+        let school = super::FishSchool::new(&super::input_generator(&i));
+
+        for days in [0, 1, 18, 80, 200] {
+            assert_eq!(
+                school.population_after_matrix(days, &super::CheckedU64),
+                Some(school.population_after(days as usize) as u64)
+            );
+        }
+
+        // Population grows by roughly 1.09x per day, so u64 runs out well before u128
+        assert_eq!(
+            school.population_after_matrix(500, &super::CheckedU64),
+            None
+        );
+        let big = school
+            .population_after_matrix(800, &super::CheckedU128)
+            .expect("800 days fits in a u128");
+        assert_eq!(
+            school.population_after_matrix(1000, &super::CheckedU128),
+            None
+        );
+
+        let modulus = 1_000_000_007;
+        assert_eq!(
+            school.population_after_matrix(800, &super::Modular::new(modulus)),
+            Some((big % modulus as u128) as u64)
+        );
+        assert!(school
+            .population_after_matrix(u64::MAX, &super::Modular::new(modulus))
+            .is_some());
+    }
+
+    #[test]
+    fn empty_school_matrix() {
+        let school = super::FishSchool::new(&[]);
+
+        assert_eq!(
+            school.population_after_matrix(10_000, &super::CheckedU64),
+            Some(0)
+        );
+        assert_eq!(
+            school.population_after_matrix(u64::MAX, &super::CheckedU128),
+            Some(0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Modulus must not be 0")]
+    fn zero_modulus() {
+        super::Modular::new(0);
+    }
 }
@@ -10,109 +10,182 @@ pub fn input_generator(input: &str) -> Vec<u32> {
         .collect()
 }
 
-// This is the naive solution, we test every possibility in the input, technically this could fail
-// if the optimal location was not one included in the input, but that would eliminate any 0's in the math
-// so I didn't run into it this is pretty slow overall since it employs no real "intelligence"
-pub fn part1(input: &[u32]) -> usize {
-    let mut most_efficient = None;
+/// Fuel needed for a single crab to move `distance` positions
+pub trait CostFunction {
+    fn cost(&self, distance: u32) -> usize;
+
+    fn total_cost(&self, crabs: &[u32], position: u32) -> usize {
+        crabs
+            .iter()
+            .map(|&crab| self.cost(crab.abs_diff(position)))
+            .sum()
+    }
+}
+
+/// Every step costs 1 fuel
+#[derive(Debug, Clone, Copy)]
+pub struct Linear;
+
+impl CostFunction for Linear {
+    fn cost(&self, distance: u32) -> usize {
+        distance as usize
+    }
+}
+
+/// Each step costs 1 more fuel than the last, so moving `n` costs `1 + 2 + ... + n`
+#[derive(Debug, Clone, Copy)]
+pub struct Triangular;
+
+impl CostFunction for Triangular {
+    fn cost(&self, distance: u32) -> usize {
+        let distance = distance as usize;
+        (distance * (distance + 1)) / 2
+    }
+}
+
+/// Moving `n` costs `n * n` fuel
+#[derive(Debug, Clone, Copy)]
+pub struct Quadratic;
+
+impl CostFunction for Quadratic {
+    fn cost(&self, distance: u32) -> usize {
+        let distance = distance as usize;
+        distance * distance
+    }
+}
+
+impl<F: Fn(u32) -> usize> CostFunction for F {
+    fn cost(&self, distance: u32) -> usize {
+        self(distance)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub position: u32,
+    pub cost: usize,
+}
 
-    for ea in input {
-        let fuel_cost: usize = input
-            .into_iter()
-            .map(|&x| (*ea as i32 - x as i32).abs() as usize)
-            .sum();
+/// Find the cheapest position using a ternary search between the outermost crabs. This relies on the
+/// total cost being convex, which holds whenever the per crab cost is non-decreasing and convex in distance.
+/// Returns `None` when there are no crabs to align.
+pub fn optimize<C: CostFunction + ?Sized>(crabs: &[u32], cost_fn: &C) -> Option<Alignment> {
+    let mut low = *crabs.iter().min()?;
+    let mut high = *crabs.iter().max()?;
 
-        if let Some(cost) = most_efficient {
-            if fuel_cost < cost {
-                most_efficient = Some(fuel_cost)
+    while high - low > 2 {
+        let third = (high - low) / 3;
+        let left = low + third;
+        let right = high - third;
+
+        match cost_fn
+            .total_cost(crabs, left)
+            .cmp(&cost_fn.total_cost(crabs, right))
+        {
+            std::cmp::Ordering::Less => high = right - 1,
+            std::cmp::Ordering::Greater => low = left + 1,
+            // A convex curve has a minimum between two equal points
+            std::cmp::Ordering::Equal => {
+                low = left;
+                high = right;
             }
-        } else {
-            most_efficient = Some(fuel_cost)
         }
     }
 
-    most_efficient.unwrap()
+    optimize_exhaustive(crabs, cost_fn, low..=high)
 }
 
-// This approach sorts the input, by doing so we know that relocating everyone to he median value location, we
-// are going to get the best solution this also has the added benefit of being significantly faster since we
-// only find one fuel cost
-pub fn part1_sorted(input: &[u32]) -> usize {
-    let mut sorted_input = input.to_vec();
-    sorted_input.sort();
+/// Check the cost of every position in `positions` and return the cheapest, works for any cost function
+pub fn optimize_exhaustive<C, I>(crabs: &[u32], cost_fn: &C, positions: I) -> Option<Alignment>
+where
+    C: CostFunction + ?Sized,
+    I: IntoIterator<Item = u32>,
+{
+    positions
+        .into_iter()
+        .map(|position| Alignment {
+            position,
+            cost: cost_fn.total_cost(crabs, position),
+        })
+        .min_by_key(|alignment| alignment.cost)
+}
 
-    let middle_location = sorted_input[(sorted_input.len() / 2) - 1];
+// Ternary search over the convex fuel curve, this only evaluates the total cost at a logarithmic
+// number of positions instead of at every crab
+pub fn part1(input: &[u32]) -> usize {
+    optimize(input, &Linear).expect("No crabs to align").cost
+}
 
-    sorted_input
-        .into_iter()
-        .map(|x| (x as i32 - middle_location as i32).abs() as usize)
-        .sum()
+/// The lower median of the crabs is always an optimal position for `Linear` costs
+pub fn median_alignment(crabs: &[u32]) -> Option<Alignment> {
+    if crabs.is_empty() {
+        return None;
+    }
+
+    let mut sorted_crabs = crabs.to_vec();
+    sorted_crabs.sort_unstable();
+    let position = sorted_crabs[(sorted_crabs.len() - 1) / 2];
+
+    Some(Alignment {
+        position,
+        cost: Linear.total_cost(&sorted_crabs, position),
+    })
 }
 
-// Here we are taking the average value since the average position of the crabs should give us
-// the cheapest place to relocate everyone, since the average comes out to a fraction, rounding it
-// *should* work, but doesn't, so we just check both sides of the fractional average, if we could
-// reposition to a finer resolution, the average should give us the best answer.
-pub fn part2(input: &[u32]) -> usize {
-    let average_value_high =
-        (input.into_iter().sum::<u32>() as f32 / input.len() as f32).ceil() as usize;
-    let average_value_low = average_value_high - 1;
+/// The real valued optimum for `Triangular` costs is within 1/2 of the mean, so the best integer position
+/// is at most one step outside of `floor(mean)..=ceil(mean)`. The mean is kept as an exact integer fraction.
+pub fn mean_alignment(crabs: &[u32]) -> Option<Alignment> {
+    let min = *crabs.iter().min()?;
+    let max = *crabs.iter().max()?;
 
-    let avh = input
-        .into_iter()
-        .map(|&x| {
-            let distance = (x as i32 - average_value_high as i32).abs() as usize;
+    let sum: u64 = crabs.iter().map(|&crab| crab as u64).sum();
+    let len = crabs.len() as u64;
+    let floor = (sum / len) as u32;
+    let ceil = sum.div_ceil(len) as u32;
 
-            (distance * (distance + 1)) / 2
-        })
-        .sum::<usize>();
+    optimize_exhaustive(
+        crabs,
+        &Triangular,
+        floor.saturating_sub(1).max(min)..=ceil.saturating_add(1).min(max),
+    )
+}
 
-    let avl = input
-        .into_iter()
-        .map(|&x| {
-            let distance = (x as i32 - average_value_low as i32).abs() as usize;
+// This approach sorts the input, by doing so we know that relocating everyone to the median value location, we
+// are going to get the best solution this also has the added benefit of being significantly faster since we
+// only find one fuel cost
+pub fn part1_sorted(input: &[u32]) -> usize {
+    median_alignment(input).map_or(0, |alignment| alignment.cost)
+}
 
-            (distance * (distance + 1)) / 2
-        })
-        .sum::<usize>();
+// Here we are taking the average value since the average position of the crabs should give us
+// the cheapest place to relocate everyone, since the average comes out to a fraction we check the
+// integer positions around it, if we could reposition to a finer resolution, the average would be
+// within half a step of the best answer.
+pub fn part2(input: &[u32]) -> usize {
+    mean_alignment(input).map_or(0, |alignment| alignment.cost)
+}
 
-    std::cmp::min(avl, avh)
+pub fn part2_ternary(input: &[u32]) -> usize {
+    optimize(input, &Triangular)
+        .expect("No crabs to align")
+        .cost
 }
 
 // We loop over every value from the minimum in the array to the maximum, check
 // the fuel cost for every single position, and just return the lowest one, foolproof
 // but very slow comparitively
 pub fn part2_naive(input: &[u32]) -> usize {
-    let mut most_efficient = None;
-
     let min = *input.into_iter().min().unwrap();
     let max = *input.into_iter().max().unwrap();
 
-    for ea in min..max {
-        let fuel_cost: usize = input
-            .into_iter()
-            .map(|&x| {
-                let distance = (ea as i32 - x as i32).abs() as usize;
-
-                (distance * (distance + 1)) / 2
-            })
-            .sum();
-
-        if let Some(cost) = most_efficient {
-            if fuel_cost < cost {
-                most_efficient = Some(fuel_cost)
-            }
-        } else {
-            most_efficient = Some(fuel_cost)
-        }
-    }
-
-    most_efficient.unwrap()
+    optimize_exhaustive(input, &Triangular, min..=max)
+        .expect("No crabs to align")
+        .cost
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -124,7 +197,7 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
@@ -133,4 +206,112 @@ mod tests {
     test!(part1_sorted, 37);
     test!(part2, 168);
     test!(part2_naive, 168);
+    test!(part2_ternary, 168);
+
+    #[test]
+    fn optimize_positions() {
+        use super::{optimize, optimize_exhaustive, Alignment, Linear, Quadratic, Triangular};
+
+        let i = read_input_file("input/2021/day7_test.txt");
+        let input = super::input_generator(&i);
+
+        assert_eq!(
+            optimize(&input, &Linear),
+            Some(Alignment {
+                position: 2,
+                cost: 37
+            })
+        );
+        assert_eq!(
+            optimize(&input, &Triangular),
+            Some(Alignment {
+                position: 5,
+                cost: 168
+            })
+        );
+        assert_eq!(
+            optimize(&input, &Quadratic),
+            optimize_exhaustive(&input, &Quadratic, 0..=16)
+        );
+
+        let cubic = |distance: u32| (distance as usize).pow(3);
+        assert_eq!(
+            optimize(&input, &cubic),
+            optimize_exhaustive(&input, &cubic, 0..=16)
+        );
+
+        assert_eq!(optimize(&[], &Linear), None);
+    }
+
+    #[test]
+    fn alignment_edge_cases() {
+        use super::{mean_alignment, median_alignment, Alignment};
+
+        assert_eq!(median_alignment(&[]), None);
+        assert_eq!(mean_alignment(&[]), None);
+        assert_eq!(super::part1_sorted(&[]), 0);
+        assert_eq!(super::part2(&[]), 0);
+
+        let single = Alignment {
+            position: 7,
+            cost: 0,
+        };
+        assert_eq!(median_alignment(&[7]), Some(single));
+        assert_eq!(mean_alignment(&[7]), Some(single));
+
+        let zero = Alignment {
+            position: 0,
+            cost: 0,
+        };
+        assert_eq!(mean_alignment(&[0, 0, 0]), Some(zero));
+
+        // Large enough that an f32 mean would lose precision
+        let large = vec![u32::MAX - 3, u32::MAX - 1, u32::MAX];
+        assert_eq!(
+            mean_alignment(&large),
+            Some(Alignment {
+                position: u32::MAX - 1,
+                cost: 4
+            })
+        );
+        assert_eq!(
+            median_alignment(&large),
+            Some(Alignment {
+                position: u32::MAX - 1,
+                cost: 3
+            })
+        );
+    }
+
+    #[test]
+    fn alignment_matches_naive() {
+        use super::{
+            mean_alignment, median_alignment, optimize_exhaustive, CostFunction, Linear, Triangular,
+        };
+
+        // xorshift so the cases are random but reproducible
+        let mut state: u64 = 0x2021_0007;
+        let mut next = |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        for _ in 0..500 {
+            let len = next(20) as usize + 1;
+            let spread = next(2000) + 1;
+            let crabs: Vec<u32> = (0..len).map(|_| next(spread) as u32).collect();
+            let range = *crabs.iter().min().unwrap()..=*crabs.iter().max().unwrap();
+
+            let mean = mean_alignment(&crabs).unwrap();
+            assert_eq!(mean.cost, super::part2_naive(&crabs), "{:?}", crabs);
+            assert_eq!(mean.cost, Triangular.total_cost(&crabs, mean.position));
+
+            let median = median_alignment(&crabs).unwrap();
+            let naive = optimize_exhaustive(&crabs, &Linear, range).unwrap();
+            assert_eq!(median.cost, naive.cost, "{:?}", crabs);
+            assert_eq!(median.cost, Linear.total_cost(&crabs, median.position));
+        }
+    }
 }
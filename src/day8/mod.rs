@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use fnv::{FnvHashMap, FnvHashSet};
 use lazy_static::lazy_static;
 
@@ -354,6 +356,400 @@ impl SegmentDisplay {
     }
 }
 
+/// A display description for the generic decoder, every glyph is the set of segments lit to show its symbol.
+/// Scrambled wires are named with the same characters as the segments they were meant to drive.
+#[derive(Debug, Clone)]
+pub struct GlyphTable {
+    segments: Vec<char>,
+    glyphs: Vec<(u32, char)>,
+}
+
+/// Result of solving the wire permutation for a set of signal patterns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decoding {
+    Unique(WireMapping),
+    /// At least two different mappings explain the signal patterns, up to `GlyphTable::AMBIGUITY_LIMIT` are returned
+    Ambiguous(Vec<WireMapping>),
+    Impossible,
+}
+
+/// `segment_for_wire[w]` is the index of the segment that wire `w` actually drives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireMapping {
+    segment_for_wire: Vec<usize>,
+}
+
+impl GlyphTable {
+    pub const AMBIGUITY_LIMIT: usize = 8;
+
+    pub fn new(segments: &str, glyphs: &[(&str, char)]) -> Self {
+        let segments: Vec<char> = segments.chars().collect();
+        assert!(
+            segments.len() <= 32,
+            "GlyphTable only supports displays with up to 32 segments"
+        );
+
+        let mut table = GlyphTable {
+            segments,
+            glyphs: Vec::new(),
+        };
+        table.glyphs = glyphs
+            .iter()
+            .map(|(lit, symbol)| {
+                (
+                    table
+                        .mask(lit)
+                        .expect("Glyph uses a segment that is not part of the display"),
+                    *symbol,
+                )
+            })
+            .collect();
+
+        for (idx, (glyph, symbol)) in table.glyphs.iter().enumerate() {
+            assert!(
+                table.glyphs[idx + 1..]
+                    .iter()
+                    .all(|(other, _)| other != glyph),
+                "Glyph for {} is lit the same as another glyph",
+                symbol
+            );
+        }
+
+        table
+    }
+
+    /// The standard seven-segment digits used by the puzzle
+    pub fn seven_segment_digits() -> Self {
+        GlyphTable::new("abcdefg", &GlyphTable::seven_segment_digit_glyphs())
+    }
+
+    /// Seven-segment digits extended with the usual `A b C d E F` hex glyphs
+    pub fn seven_segment_hex() -> Self {
+        let mut glyphs = GlyphTable::seven_segment_digit_glyphs();
+        glyphs.extend([
+            ("abcdef", 'A'),
+            ("bdefg", 'b'),
+            ("abeg", 'C'),
+            ("cdefg", 'd'),
+            ("abdeg", 'E'),
+            ("abde", 'F'),
+        ]);
+
+        GlyphTable::new("abcdefg", &glyphs)
+    }
+
+    fn seven_segment_digit_glyphs() -> Vec<(&'static str, char)> {
+        let mut glyphs: Vec<(&str, char)> = STR_TO_VAL_ORACLE
+            .iter()
+            .map(|(&lit, &symbol)| (lit, symbol))
+            .collect();
+        glyphs.sort_by_key(|&(_, symbol)| symbol);
+        glyphs
+    }
+
+    fn mask(&self, pattern: &str) -> Option<u32> {
+        pattern.chars().try_fold(0, |mask, c| {
+            let idx = self.segments.iter().position(|&seg| seg == c)?;
+            Some(mask | 1 << idx)
+        })
+    }
+
+    /// Solve the wire permutation by assigning each distinct signal pattern to a glyph with the same number of
+    /// lit segments, narrowing the candidate segments of every wire and backtracking when a wire runs out
+    pub fn solve(&self, signal_patterns: &[&str]) -> Decoding {
+        let mut patterns = match signal_patterns
+            .iter()
+            .map(|pattern| self.mask(pattern))
+            .collect::<Option<Vec<u32>>>()
+        {
+            Some(patterns) => patterns,
+            None => return Decoding::Impossible,
+        };
+        patterns.sort_unstable();
+        patterns.dedup();
+
+        // Patterns with the fewest same sized glyphs narrow things down the most, so try those first
+        patterns.sort_by_key(|pattern| {
+            self.glyphs
+                .iter()
+                .filter(|(glyph, _)| glyph.count_ones() == pattern.count_ones())
+                .count()
+        });
+
+        let all_segments = ((1u64 << self.segments.len()) - 1) as u32;
+        let mut solutions = Vec::new();
+        self.search(
+            &patterns,
+            &mut vec![false; self.glyphs.len()],
+            vec![all_segments; self.segments.len()],
+            &mut solutions,
+        );
+
+        match solutions.len() {
+            0 => Decoding::Impossible,
+            1 => Decoding::Unique(solutions.remove(0)),
+            _ => Decoding::Ambiguous(solutions),
+        }
+    }
+
+    fn search(
+        &self,
+        patterns: &[u32],
+        used_glyphs: &mut [bool],
+        candidates: Vec<u32>,
+        solutions: &mut Vec<WireMapping>,
+    ) {
+        let pattern = match patterns.first() {
+            Some(&pattern) => pattern,
+            None => {
+                self.permutations(&candidates, 0, 0, &mut Vec::new(), solutions);
+                return;
+            }
+        };
+
+        for glyph_idx in 0..self.glyphs.len() {
+            let glyph = self.glyphs[glyph_idx].0;
+            if used_glyphs[glyph_idx] || glyph.count_ones() != pattern.count_ones() {
+                continue;
+            }
+
+            // Lit wires have to drive a lit segment of the glyph, unlit wires have to drive an unlit one
+            let mut narrowed = candidates.clone();
+            for (wire, candidate) in narrowed.iter_mut().enumerate() {
+                if pattern & (1 << wire) != 0 {
+                    *candidate &= glyph;
+                } else {
+                    *candidate &= !glyph;
+                }
+            }
+
+            if !propagate(&mut narrowed) {
+                continue;
+            }
+
+            used_glyphs[glyph_idx] = true;
+            self.search(&patterns[1..], used_glyphs, narrowed, solutions);
+            used_glyphs[glyph_idx] = false;
+
+            if solutions.len() >= GlyphTable::AMBIGUITY_LIMIT {
+                return;
+            }
+        }
+    }
+
+    /// Enumerate every one to one wire mapping that is still allowed by `candidates`
+    fn permutations(
+        &self,
+        candidates: &[u32],
+        wire: usize,
+        taken: u32,
+        current: &mut Vec<usize>,
+        solutions: &mut Vec<WireMapping>,
+    ) {
+        if solutions.len() >= GlyphTable::AMBIGUITY_LIMIT {
+            return;
+        }
+
+        if wire == candidates.len() {
+            solutions.push(WireMapping {
+                segment_for_wire: current.clone(),
+            });
+            return;
+        }
+
+        let mut options = candidates[wire] & !taken;
+        while options != 0 {
+            let segment = options.trailing_zeros() as usize;
+            options &= options - 1;
+
+            current.push(segment);
+            self.permutations(
+                candidates,
+                wire + 1,
+                taken | 1 << segment,
+                current,
+                solutions,
+            );
+            current.pop();
+        }
+    }
+}
+
+/// Any wire with a single candidate segment claims it from every other wire, returns `false` once a wire has no
+/// candidates left
+fn propagate(candidates: &mut [u32]) -> bool {
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for wire in 0..candidates.len() {
+            match candidates[wire].count_ones() {
+                0 => return false,
+                1 => {
+                    let claimed = candidates[wire];
+                    for (other, candidate) in candidates.iter_mut().enumerate() {
+                        if other != wire && *candidate & claimed != 0 {
+                            *candidate &= !claimed;
+                            changed = true;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    true
+}
+
+impl WireMapping {
+    /// Translate a scrambled pattern to the symbol it shows, `None` if the result is not a glyph in `table`
+    pub fn decode(&self, table: &GlyphTable, pattern: &str) -> Option<char> {
+        let scrambled = table.mask(pattern)?;
+        let mask = self
+            .segment_for_wire
+            .iter()
+            .enumerate()
+            .filter(|&(wire, _)| scrambled & (1 << wire) != 0)
+            .fold(0, |mask, (_, &segment)| mask | 1 << segment);
+
+        table
+            .glyphs
+            .iter()
+            .find(|&&(glyph, _)| glyph == mask)
+            .map(|&(_, symbol)| symbol)
+    }
+}
+
+/// Reasons a `NoteEntry` cannot be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// A note needs exactly one signal pattern for each of the 10 digits
+    SignalCount(usize),
+    DuplicatePattern(String),
+    /// A pattern contains a wire outside of `a` to `g`, or uses the same wire twice
+    InvalidPattern(String),
+    /// An output value is not one of the signal patterns in any order
+    UnknownOutput(String),
+    /// The signal patterns don't describe a rewired seven-segment display
+    Unsolvable,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::SignalCount(count) => {
+                write!(f, "expected 10 signal patterns, found {}", count)
+            }
+            DecodeError::DuplicatePattern(pattern) => {
+                write!(f, "signal pattern \"{}\" appears more than once", pattern)
+            }
+            DecodeError::InvalidPattern(pattern) => {
+                write!(f, "\"{}\" is not a valid set of wires a-g", pattern)
+            }
+            DecodeError::UnknownOutput(output) => write!(
+                f,
+                "output value \"{}\" does not match any signal pattern",
+                output
+            ),
+            DecodeError::Unsolvable => write!(
+                f,
+                "signal patterns do not match the seven-segment digits under any wiring"
+            ),
+        }
+    }
+}
+
+/// A `DecodeError` along with the 1-based line of the note it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteDiagnostic {
+    pub line: usize,
+    pub error: DecodeError,
+}
+
+impl Display for NoteDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+/// Bitmask of the wires in `pattern`, `None` for unknown or repeated wires
+fn wire_mask(pattern: &str) -> Option<u8> {
+    pattern.bytes().try_fold(0u8, |mask, wire| {
+        let bit = 1u8.checked_shl(wire.checked_sub(b'a')? as u32)?;
+        if bit & 0x7f == 0 || mask & bit != 0 {
+            None
+        } else {
+            Some(mask | bit)
+        }
+    })
+}
+
+impl NoteEntry<'_> {
+    /// Check the note is self consistent, which is everything `decode` needs other than solvability
+    pub fn validate(&self) -> Result<(), DecodeError> {
+        let mut seen = Vec::with_capacity(self.signal_patterns.len());
+        for pattern in &self.signal_patterns {
+            let mask = wire_mask(pattern)
+                .ok_or_else(|| DecodeError::InvalidPattern(pattern.to_string()))?;
+            if seen.contains(&mask) {
+                return Err(DecodeError::DuplicatePattern(pattern.to_string()));
+            }
+            seen.push(mask);
+        }
+
+        if self.signal_patterns.len() != 10 {
+            return Err(DecodeError::SignalCount(self.signal_patterns.len()));
+        }
+
+        for output in &self.output_values {
+            let mask =
+                wire_mask(output).ok_or_else(|| DecodeError::InvalidPattern(output.to_string()))?;
+            if !seen.contains(&mask) {
+                return Err(DecodeError::UnknownOutput(output.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decode every output value to its digit
+    pub fn decode(&self) -> Result<Vec<u8>, DecodeError> {
+        self.validate()?;
+
+        let table = GlyphTable::seven_segment_digits();
+        match table.solve(&self.signal_patterns) {
+            Decoding::Unique(mapping) => self
+                .output_values
+                .iter()
+                .map(|output| {
+                    mapping
+                        .decode(&table, output)
+                        .and_then(|digit| digit.to_digit(10))
+                        .map(|digit| digit as u8)
+                        .ok_or(DecodeError::Unsolvable)
+                })
+                .collect(),
+            _ => Err(DecodeError::Unsolvable),
+        }
+    }
+}
+
+/// Run `NoteEntry::decode` over every note and report each one that fails along with its line
+pub fn validate_notes(input: &[NoteEntry]) -> Vec<NoteDiagnostic> {
+    input
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, note_entry)| {
+            note_entry.decode().err().map(|error| NoteDiagnostic {
+                line: idx + 1,
+                error,
+            })
+        })
+        .collect()
+}
+
 pub fn input_generator(input: &str) -> Vec<NoteEntry> {
     input
         .lines()
@@ -501,9 +897,82 @@ pub fn part2_alternate(input: &[NoteEntry]) -> usize {
         .sum::<usize>()
 }
 
+/// Each pattern becomes a `u8` with one bit per wire, so every check is a popcount of an AND against the
+/// "1" and "4" patterns, and the output is built as an integer directly instead of parsing a `String`
+pub fn part2_bitmask(input: &[NoteEntry]) -> usize {
+    let to_mask = |pattern: &str| {
+        pattern
+            .bytes()
+            .fold(0u8, |mask, wire| mask | 1 << (wire - b'a'))
+    };
+
+    input
+        .iter()
+        .map(|note_entry| {
+            let mut one = 0;
+            let mut four = 0;
+            for signal in &note_entry.signal_patterns {
+                match signal.len() {
+                    2 => one = to_mask(signal),
+                    4 => four = to_mask(signal),
+                    _ => {}
+                }
+            }
+
+            note_entry.output_values.iter().fold(0, |value, output| {
+                let mask = to_mask(output);
+                let digit = match (
+                    mask.count_ones(),
+                    (mask & one).count_ones(),
+                    (mask & four).count_ones(),
+                ) {
+                    (2, _, _) => 1,
+                    (3, _, _) => 7,
+                    (4, _, _) => 4,
+                    (7, _, _) => 8,
+                    (5, 2, _) => 3,
+                    (5, _, 3) => 5,
+                    (5, _, _) => 2,
+                    (6, 1, _) => 6,
+                    (6, _, 4) => 9,
+                    (6, _, _) => 0,
+                    _ => unreachable!("No numbers should be left unparsed"),
+                };
+
+                value * 10 + digit
+            })
+        })
+        .sum::<usize>()
+}
+
+/// Uses the generic `GlyphTable` decoder, which knows nothing about the shape of the digits
+pub fn part2_generic(input: &[NoteEntry]) -> usize {
+    let table = GlyphTable::seven_segment_digits();
+
+    input
+        .iter()
+        .map(
+            |note_entry| match table.solve(&note_entry.signal_patterns) {
+                Decoding::Unique(mapping) => note_entry
+                    .output_values
+                    .iter()
+                    .map(|output| {
+                        mapping
+                            .decode(&table, output)
+                            .expect("Output value is not a known digit")
+                    })
+                    .collect::<String>()
+                    .parse::<usize>()
+                    .expect("Cannot parse output value"),
+                decoding => panic!("Signal patterns did not solve uniquely: {:?}", decoding),
+            },
+        )
+        .sum::<usize>()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -515,7 +984,7 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
@@ -523,4 +992,141 @@ mod tests {
     test!(part1, 26);
     test!(part2, 61229);
     test!(part2_alternate, 61229);
+    test!(part2_generic, 61229);
+    test!(part2_bitmask, 61229);
+
+    #[test]
+    fn note_diagnostics() {
+        use super::{DecodeError, NoteDiagnostic};
+
+        let i = read_input_file("input/2021/day8_test.txt");
+        let input = super::input_generator(&i);
+        assert_eq!(super::validate_notes(&input), vec![]);
+        assert_eq!(input[0].decode(), Ok(vec![8, 3, 9, 4]));
+
+        let bad = super::input_generator(
+            "be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe
+be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd | fdgacbe cefdb cefbgd gcbe
+be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd bcdfe | fdgacbe cefdb cefbgd gcbe
+be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcb
+be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edx | fdgacbe cefdb cefbgd gcbe
+be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd ed | fdgacbe cefdb cefbgd gcbe",
+        );
+
+        let diagnostics = super::validate_notes(&bad);
+        assert_eq!(
+            diagnostics,
+            vec![
+                NoteDiagnostic {
+                    line: 2,
+                    error: DecodeError::SignalCount(9)
+                },
+                NoteDiagnostic {
+                    line: 3,
+                    error: DecodeError::DuplicatePattern("bcdfe".to_string())
+                },
+                NoteDiagnostic {
+                    line: 4,
+                    error: DecodeError::UnknownOutput("gcb".to_string())
+                },
+                NoteDiagnostic {
+                    line: 5,
+                    error: DecodeError::InvalidPattern("edx".to_string())
+                },
+                NoteDiagnostic {
+                    line: 6,
+                    error: DecodeError::Unsolvable
+                },
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "line 2: expected 10 signal patterns, found 9"
+        );
+    }
+
+    #[test]
+    fn glyph_table_hex() {
+        use super::{Decoding, GlyphTable};
+
+        let table = GlyphTable::seven_segment_hex();
+        // Shuffle the wires and write every hex glyph with them
+        let scramble = |lit: &str| -> String {
+            lit.chars()
+                .map(|c| match c {
+                    'a' => 'd',
+                    'b' => 'e',
+                    'c' => 'a',
+                    'd' => 'f',
+                    'e' => 'g',
+                    'f' => 'b',
+                    'g' => 'c',
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+        let shown = [
+            "abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "acf", "abcdefg",
+            "abcdfg", "abcdef", "bdefg", "abeg", "cdefg", "abdeg", "abde",
+        ];
+        let scrambled: Vec<String> = shown.iter().map(|lit| scramble(lit)).collect();
+        let patterns: Vec<&str> = scrambled.iter().map(|x| x.as_str()).collect();
+
+        match table.solve(&patterns) {
+            Decoding::Unique(mapping) => {
+                let decoded: String = patterns
+                    .iter()
+                    .map(|pattern| mapping.decode(&table, pattern).unwrap())
+                    .collect();
+                assert_eq!(decoded, "0123456789AbCdEF");
+            }
+            decoding => panic!("Expected a unique decoding, got {:?}", decoding),
+        }
+
+        // "1" and "7" alone leave most of the wires undetermined
+        assert!(matches!(
+            table.solve(&["ab", "dab"]),
+            Decoding::Ambiguous(mappings) if mappings.len() == GlyphTable::AMBIGUITY_LIMIT
+        ));
+
+        // Nothing has a single segment lit
+        assert_eq!(table.solve(&["a"]), Decoding::Impossible);
+        assert_eq!(table.solve(&["xy"]), Decoding::Impossible);
+    }
+
+    #[test]
+    fn glyph_table_32_segments() {
+        use super::{Decoding, GlyphTable};
+
+        // Glyph `n` lights the first `n + 1` segments, so every glyph has a different size
+        let segments: String = ('a'..='z').chain('A'..='F').collect();
+        let lit: Vec<String> = (1..=32).map(|len| segments[..len].to_string()).collect();
+        let symbols: Vec<char> = (0..32u8).map(|n| (b'0' + n) as char).collect();
+        let glyphs: Vec<(&str, char)> = lit
+            .iter()
+            .map(|lit| lit.as_str())
+            .zip(symbols.iter().copied())
+            .collect();
+        let table = GlyphTable::new(&segments, &glyphs);
+
+        // Wire `n` drives segment `31 - n`
+        let reversed: Vec<char> = segments.chars().rev().collect();
+        let scrambled: Vec<String> = (1..=32)
+            .map(|len| reversed[..len].iter().collect())
+            .collect();
+        let patterns: Vec<&str> = scrambled.iter().map(|x| x.as_str()).collect();
+
+        match table.solve(&patterns) {
+            Decoding::Unique(mapping) => {
+                let decoded: Vec<char> = patterns
+                    .iter()
+                    .map(|pattern| mapping.decode(&table, pattern).unwrap())
+                    .collect();
+                assert_eq!(decoded, symbols);
+            }
+            decoding => panic!("Expected a unique decoding, got {:?}", decoding),
+        }
+
+        assert!(matches!(table.solve(&["F"]), Decoding::Ambiguous(_)));
+    }
 }
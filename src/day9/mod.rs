@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fmt::Display, io::Write};
 
 use fnv::{FnvHashMap, FnvHashSet};
 
@@ -72,6 +72,67 @@ impl<'a> CaveFloor {
             })
     }
 
+    /// Every basin on the floor, ordered by the position of its low point (top to bottom, left to right)
+    pub fn basins(&self) -> Vec<Basin> {
+        let mut low_points: Vec<Coordinate> = self
+            .map
+            .keys()
+            .filter(|coord| self.is_lowest_neighbor(coord))
+            .copied()
+            .collect();
+        low_points.sort();
+
+        low_points
+            .into_iter()
+            .enumerate()
+            .map(|(id, low_point)| {
+                let mut explored = FnvHashSet::default();
+                self.explore_basin(&low_point, &mut explored);
+
+                let mut cells: Vec<Coordinate> = explored.into_iter().collect();
+                cells.sort();
+
+                Basin {
+                    id,
+                    low_point,
+                    cells,
+                }
+            })
+            .collect()
+    }
+
+    /// Label every cell with the basin it belongs to
+    pub fn basin_map(&self) -> BasinMap {
+        let width = self.max_x + 1;
+        let height = self.max_y + 1;
+        let mut heights = vec![9; width * height];
+        for (coord, &value) in &self.map {
+            heights[coord.y * width + coord.x] = value;
+        }
+
+        let mut labels = vec![None; width * height];
+        let mut shared = Vec::new();
+        for basin in self.basins() {
+            for cell in &basin.cells {
+                let label = &mut labels[cell.y * width + cell.x];
+                if label.is_some() {
+                    shared.push(*cell);
+                }
+                *label = Some(basin.id);
+            }
+        }
+        shared.sort();
+        shared.dedup();
+
+        BasinMap {
+            width,
+            height,
+            heights,
+            labels,
+            shared,
+        }
+    }
+
     fn is_lowest_neighbor(&self, coord: &Coordinate) -> bool {
         let current_height = self.map.get(coord).expect("Error getting current height!");
 
@@ -103,10 +164,10 @@ impl<'a> CaveFloor {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Coordinate {
-    x: usize,
-    y: usize,
+    pub y: usize,
+    pub x: usize,
 }
 
 impl Coordinate {
@@ -139,6 +200,117 @@ impl Coordinate {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Basin {
+    pub id: usize,
+    pub low_point: Coordinate,
+    pub cells: Vec<Coordinate>,
+}
+
+impl Basin {
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+/// Cave floor where every cell carries the id of its basin, `Display` renders it with ANSI colors
+#[derive(Debug, Clone)]
+pub struct BasinMap {
+    width: usize,
+    height: usize,
+    heights: Vec<u32>,
+    labels: Vec<Option<usize>>,
+    // Cells reached from more than one low point, which only happens if 9s don't separate every basin
+    shared: Vec<Coordinate>,
+}
+
+impl BasinMap {
+    const ANSI_COLORS: [u8; 6] = [41, 42, 43, 44, 45, 46];
+
+    pub fn label(&self, coord: Coordinate) -> Option<usize> {
+        self.labels[coord.y * self.width + coord.x]
+    }
+
+    /// Cells belonging to more than one basin
+    pub fn shared_cells(&self) -> &[Coordinate] {
+        &self.shared
+    }
+
+    /// Cells lower than 9 which no basin reached
+    pub fn unassigned_cells(&self) -> Vec<Coordinate> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Coordinate { x, y }))
+            .filter(|&coord| {
+                self.heights[coord.y * self.width + coord.x] != 9 && self.label(coord).is_none()
+            })
+            .collect()
+    }
+
+    /// Does the puzzle assumption hold, every cell below 9 is in exactly one basin
+    pub fn nines_separate_basins(&self) -> bool {
+        self.shared.is_empty() && self.unassigned_cells().is_empty()
+    }
+
+    /// Write the map as a binary PPM (P6), 9s are black and each basin gets a color spread around the hue wheel
+    pub fn write_ppm<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+
+        let pixels: Vec<u8> = self
+            .labels
+            .iter()
+            .zip(&self.heights)
+            .flat_map(|(label, &height)| match label {
+                _ if height == 9 => [0, 0, 0],
+                None => [255, 255, 255],
+                Some(id) => {
+                    // Golden angle steps keep neighboring ids from getting similar colors
+                    let hue = (*id as f32 * 137.508) % 360.0;
+                    hue_to_rgb(hue)
+                }
+            })
+            .collect();
+        writer.write_all(&pixels)
+    }
+}
+
+/// Fully saturated color for `hue` in degrees
+fn hue_to_rgb(hue: f32) -> [u8; 3] {
+    let sector = hue / 60.0;
+    let rising = ((sector % 1.0) * 255.0) as u8;
+    let falling = 255 - rising;
+
+    match sector as u32 {
+        0 => [255, rising, 0],
+        1 => [falling, 255, 0],
+        2 => [0, 255, rising],
+        3 => [0, falling, 255],
+        4 => [rising, 0, 255],
+        _ => [255, 0, falling],
+    }
+}
+
+impl Display for BasinMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let height = self.heights[y * self.width + x];
+
+                match self.label(Coordinate { x, y }) {
+                    Some(id) => write!(
+                        f,
+                        "\x1b[{};30m{}\x1b[0m",
+                        Self::ANSI_COLORS[id % Self::ANSI_COLORS.len()],
+                        height
+                    )?,
+                    None => write!(f, "{}", height)?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
 pub fn input_generator(input: &str) -> CaveFloor {
     let mut map = FnvHashMap::default();
     let mut max_x = 0;
@@ -170,6 +342,130 @@ pub fn input_generator(input: &str) -> CaveFloor {
     CaveFloor { map, max_x, max_y }
 }
 
+/// Disjoint set forest over the dense grid, `parent[i] == i` marks a root
+struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        UnionFind {
+            parent: (0..len).collect(),
+            size: vec![1; len],
+        }
+    }
+
+    fn find(&mut self, mut idx: usize) -> usize {
+        while self.parent[idx] != idx {
+            // Path halving keeps the trees flat without needing recursion
+            self.parent[idx] = self.parent[self.parent[idx]];
+            idx = self.parent[idx];
+        }
+        idx
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+}
+
+impl CaveFloor {
+    /// Sizes of every region of non-9 cells, found in a single pass over the rows by joining each cell with the
+    /// cells above and to the left of it. This matches `find_basins` as long as 9s separate every basin.
+    pub fn basin_sizes_union_find(&self) -> Vec<usize> {
+        let width = self.max_x + 1;
+        let height = self.max_y + 1;
+        let mut heights = vec![9u8; width * height];
+        for (coord, &value) in &self.map {
+            heights[coord.y * width + coord.x] = value as u8;
+        }
+
+        let mut sets = UnionFind::new(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let idx = y * width + x;
+                if heights[idx] == 9 {
+                    continue;
+                }
+                if x > 0 && heights[idx - 1] != 9 {
+                    sets.union(idx, idx - 1);
+                }
+                if y > 0 && heights[idx - width] != 9 {
+                    sets.union(idx, idx - width);
+                }
+            }
+        }
+
+        (0..width * height)
+            .filter(|&idx| heights[idx] != 9 && sets.parent[idx] == idx)
+            .map(|idx| sets.size[idx])
+            .collect()
+    }
+}
+
+/// Build a `width` x `height` map in the puzzle's input format for stress testing. Roughly 30% of the cells are
+/// 9s, every other cell is its distance (capped at 8) from the first cell found in its region, so each region has
+/// exactly one low point just like the real input.
+pub fn generate_height_map(width: usize, height: usize, seed: u64) -> String {
+    let mut state = seed.max(1);
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    let mut heights: Vec<Option<u8>> = (0..width * height)
+        .map(|_| if next() % 10 < 3 { Some(9) } else { None })
+        .collect();
+
+    let mut queue = VecDeque::new();
+    for start in 0..width * height {
+        if heights[start].is_some() {
+            continue;
+        }
+
+        heights[start] = Some(0);
+        queue.push_back(start);
+        while let Some(idx) = queue.pop_front() {
+            let distance = heights[idx].expect("Queued cells always have a height");
+            let (x, y) = (idx % width, idx / width);
+            let neighbors = [
+                (y > 0).then(|| idx - width),
+                (x + 1 < width).then(|| idx + 1),
+                (y + 1 < height).then(|| idx + width),
+                (x > 0).then(|| idx - 1),
+            ];
+
+            for neighbor in neighbors.into_iter().flatten() {
+                if heights[neighbor].is_none() {
+                    heights[neighbor] = Some((distance + 1).min(8));
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    heights
+        .chunks(width)
+        .map(|row| {
+            row.iter()
+                .map(|cell| char::from(b'0' + cell.expect("Every cell has a height")))
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
 #[inline(never)]
 pub fn part1(input: &CaveFloor) -> usize {
     input.find_lowest_points()
@@ -180,9 +476,17 @@ pub fn part2(input: &CaveFloor) -> usize {
     input.find_basins()
 }
 
+#[inline(never)]
+pub fn part2_union_find(input: &CaveFloor) -> usize {
+    let mut basin_sizes = input.basin_sizes_union_find();
+    basin_sizes.sort_unstable_by(|a, b| b.cmp(a));
+
+    basin_sizes.iter().take(3).product()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::read_input_file;
+    use crate::{read_input_file, Answer};
     macro_rules! test {
         ($func:ident, $val:expr) => {
             #[test]
@@ -194,11 +498,49 @@ mod tests {
                 ));
 
                 let input = super::input_generator(&i);
-                assert_eq!(super::$func(&input), $val);
+                assert_eq!(Answer::from(super::$func(&input)), Answer::from($val));
             }
         };
     }
 
     test!(part1, 15);
     test!(part2, 1134);
+    test!(part2_union_find, 1134);
+
+    #[test]
+    fn basin_labels() {
+        use super::Coordinate;
+
+        let i = read_input_file("input/2021/day9_test.txt");
+        let input = super::input_generator(&i);
+
+        let basins = input.basins();
+        let sizes: Vec<usize> = basins.iter().map(|basin| basin.size()).collect();
+        assert_eq!(sizes, vec![3, 9, 14, 9]);
+        assert_eq!(basins[0].low_point, Coordinate { x: 1, y: 0 });
+        assert_eq!(basins[3].low_point, Coordinate { x: 6, y: 4 });
+
+        let map = input.basin_map();
+        assert!(map.nines_separate_basins());
+        assert_eq!(map.label(Coordinate { x: 0, y: 0 }), Some(0));
+        assert_eq!(map.label(Coordinate { x: 9, y: 0 }), Some(1));
+        assert_eq!(map.label(Coordinate { x: 2, y: 0 }), None);
+
+        // No 9 between the two low points, so both explore the same cells
+        let joined = super::input_generator("1021");
+        let map = joined.basin_map();
+        assert!(!map.nines_separate_basins());
+        assert_eq!(map.shared_cells().len(), 4);
+    }
+
+    #[test]
+    fn synthetic_union_find() {
+        for seed in 1..5 {
+            let raw = super::generate_height_map(80, 60, seed);
+            let input = super::input_generator(&raw);
+
+            assert!(input.basin_map().nines_separate_basins());
+            assert_eq!(super::part2_union_find(&input), super::part2(&input));
+        }
+    }
 }
@@ -1,5 +1,3 @@
-#![feature(hash_drain_filter)]
-
 pub mod day1;
 pub mod day10;
 pub mod day11;
@@ -18,9 +16,106 @@ pub mod day7;
 pub mod day8;
 pub mod day9;
 
+use std::fmt::Display;
+
 pub fn read_input_file(input: &str) -> String {
     std::fs::read_to_string(input)
         .expect("Error while reading provided file name")
         .trim()
         .to_string()
 }
+
+/// A puzzle answer, so solutions that aren't numbers can be printed and checked the same way as the ones that are
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Answer {
+    Unsigned(u128),
+    /// Only used for negative numbers, anything else converts to `Unsigned` so equal values compare equal
+    Signed(i128),
+    Text(String),
+    /// Rows of a picture, like the dots on day13's paper
+    Grid(Vec<String>),
+}
+
+impl Answer {
+    pub fn grid(picture: &str) -> Self {
+        Answer::Grid(picture.lines().map(str::to_owned).collect())
+    }
+}
+
+impl Display for Answer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Answer::Unsigned(value) => write!(f, "{}", value),
+            Answer::Signed(value) => write!(f, "{}", value),
+            Answer::Text(text) => write!(f, "{}", text),
+            // Start on a fresh line so the rows line up
+            Answer::Grid(rows) => rows.iter().try_for_each(|row| write!(f, "\n{}", row)),
+        }
+    }
+}
+
+macro_rules! answer_from_unsigned {
+    ($($int:ty),+) => {
+        $(impl From<$int> for Answer {
+            fn from(value: $int) -> Self {
+                Answer::Unsigned(value as u128)
+            }
+        })+
+    };
+}
+
+macro_rules! answer_from_signed {
+    ($($int:ty),+) => {
+        $(impl From<$int> for Answer {
+            fn from(value: $int) -> Self {
+                if value < 0 {
+                    Answer::Signed(value as i128)
+                } else {
+                    Answer::Unsigned(value as u128)
+                }
+            }
+        })+
+    };
+}
+
+answer_from_unsigned!(u8, u16, u32, u64, u128, usize);
+answer_from_signed!(i8, i16, i32, i64, i128, isize);
+
+impl From<String> for Answer {
+    fn from(text: String) -> Self {
+        Answer::Text(text)
+    }
+}
+
+impl From<&str> for Answer {
+    fn from(text: &str) -> Self {
+        Answer::Text(text.to_owned())
+    }
+}
+
+impl From<Vec<String>> for Answer {
+    fn from(rows: Vec<String>) -> Self {
+        Answer::Grid(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Answer;
+
+    #[test]
+    fn answer_conversions() {
+        assert_eq!(Answer::from(45isize), Answer::from(45usize));
+        assert_eq!(Answer::from(-3i64), Answer::Signed(-3));
+        assert_eq!(Answer::from("PFKL"), Answer::from("PFKL".to_string()));
+        assert_ne!(Answer::from("12"), Answer::from(12));
+
+        let grid = Answer::grid("#..\n.#.");
+        assert_eq!(
+            grid,
+            Answer::from(vec!["#..".to_string(), ".#.".to_string()])
+        );
+        assert_eq!(grid.to_string(), "\n#..\n.#.");
+        assert_eq!(Answer::from(u64::MAX).to_string(), "18446744073709551615");
+    }
+}
@@ -10,10 +10,10 @@ macro_rules! run {
             println!("================= {} =================", stringify!($lib));
 
             let part1 = $lib::part1(&formatted_input);
-            println!("Solution for {} Part 1 : {}", stringify!($lib), part1);
+            println!("Solution for {} Part 1 : {}", stringify!($lib), Answer::from(part1));
 
             let part2 = $lib::part2(&formatted_input);
-            println!("Solution for {} Part 2: {}", stringify!($lib), part2);
+            println!("Solution for {} Part 2: {}", stringify!($lib), Answer::from(part2));
         }
     };
     ($lib:ident, $($func:ident), +) => {
@@ -25,12 +25,12 @@ macro_rules! run {
             println!("================= {} =================", stringify!($lib));
 
             let part1 = $lib::part1(&formatted_input);
-            println!("Solution for {} Part 1 : {}", stringify!($lib), part1);
+            println!("Solution for {} Part 1 : {}", stringify!($lib), Answer::from(part1));
 
             let part2 = $lib::part2(&formatted_input);
-            println!("Solution for {} Part 2: {}", stringify!($lib), part2);
+            println!("Solution for {} Part 2: {}", stringify!($lib), Answer::from(part2));
 
-            $(println!("Solution for {} {} : {}", stringify!($lib), stringify!($func), $lib::$func(&formatted_input));)*
+            $(println!("Solution for {} {} : {}", stringify!($lib), stringify!($func), Answer::from($lib::$func(&formatted_input)));)*
         }
     }
 }
@@ -45,10 +45,10 @@ macro_rules! run_mut {
             println!("================= {} =================", stringify!($lib));
 
             let part1 = $lib::part1(&mut formatted_input);
-            println!("Solution for {} Part 1 : {}", stringify!($lib), part1);
+            println!("Solution for {} Part 1 : {}", stringify!($lib), Answer::from(part1));
 
             let part2 = $lib::part2(&mut formatted_input);
-            println!("Solution for {} Part 2: {}", stringify!($lib), part2);
+            println!("Solution for {} Part 2: {}", stringify!($lib), Answer::from(part2));
         }
     };
     ($lib:ident, $($func:ident), +) => {
@@ -60,12 +60,12 @@ macro_rules! run_mut {
             println!("================= {} =================", stringify!($lib));
 
             let part1 = $lib::part1(&mut formatted_input);
-            println!("Solution for {} Part 1 : {}", stringify!($lib), part1);
+            println!("Solution for {} Part 1 : {}", stringify!($lib), Answer::from(part1));
 
             let part2 = $lib::part2(&mut formatted_input);
-            println!("Solution for {} Part 2: {}", stringify!($lib), part2);
+            println!("Solution for {} Part 2: {}", stringify!($lib), Answer::from(part2));
 
-            $(println!("Solution for {} {} : {}", stringify!($lib), stringify!($func), $lib::$func(&formatted_input));)*
+            $(println!("Solution for {} {} : {}", stringify!($lib), stringify!($func), Answer::from($lib::$func(&formatted_input)));)*
         }
     }
 }
@@ -76,14 +76,14 @@ fn main() {
     run!(day3);
     run_mut!(day4);
     run!(day5);
-    run!(day6);
-    run!(day7, part1_sorted, part2_naive);
-    run!(day8, part2_alternate);
-    run!(day9);
-    run!(day10);
-    run!(day11);
-    run!(day12);
-    run!(day13);
+    run!(day6, part2_matrix);
+    run!(day7, part1_sorted, part2_naive, part2_ternary);
+    run!(day8, part2_alternate, part2_generic, part2_bitmask);
+    run!(day9, part2_union_find);
+    run!(day10, part1_streaming, part2_streaming);
+    run!(day11, part1_dense, part2_dense);
+    run!(day12, part1_memoized, part2_memoized, part2_policy);
+    run!(day13, part2_grid);
     run!(day14, part1_slow);
     // run!(day15);
     run!(day16);